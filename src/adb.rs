@@ -0,0 +1,252 @@
+use crate::error::PistonError;
+use std::fs::{File, create_dir_all};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//staging area for files moved in and out of an app's sandbox with run-as
+const DEVICE_STAGING_DIR: &str = "/data/local/tmp";
+
+//device operations for a single adb connected android device
+pub struct Adb {
+    adb_path: String,
+    device_id: String,
+}
+
+impl Adb {
+    pub fn new(adb_path: String, device_id: String) -> Self {
+        Adb {
+            adb_path,
+            device_id,
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.adb_path);
+        command.arg("-s").arg(&self.device_id);
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Output, PistonError> {
+        let output = self
+            .command()
+            .args(args)
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(format!("Failed to run adb: {}", e)))?;
+        if !output.status.success() {
+            return Err(PistonError::ADBDevicesError(format!(
+                "adb {} failed: {}{}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(output)
+    }
+
+    pub fn uninstall(&self, package: &str) -> Result<(), PistonError> {
+        self.run(&["uninstall", package])?;
+        println!("uninstalled {} from {}", package, self.device_id);
+        Ok(())
+    }
+
+    pub fn clear_data(&self, package: &str) -> Result<(), PistonError> {
+        self.run(&["shell", "pm", "clear", package])?;
+        println!("cleared app data of {} on {}", package, self.device_id);
+        Ok(())
+    }
+
+    pub fn force_stop(&self, package: &str) -> Result<(), PistonError> {
+        self.run(&["shell", "am", "force-stop", package])?;
+        println!("force stopped {} on {}", package, self.device_id);
+        Ok(())
+    }
+
+    //target/piston/captures/<kind>-<device>-<unix time>.<ext>
+    fn capture_path(
+        &self,
+        captures_dir: &Path,
+        kind: &str,
+        ext: &str,
+    ) -> Result<PathBuf, PistonError> {
+        create_dir_all(captures_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: captures_dir.to_path_buf(),
+            source: e,
+        })?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let device = self.device_id.replace([':', '/'], "_");
+        Ok(captures_dir.join(format!("{}-{}-{}.{}", kind, device, timestamp, ext)))
+    }
+
+    pub fn screenshot(&self, captures_dir: &Path) -> Result<PathBuf, PistonError> {
+        let path = self.capture_path(captures_dir, "screenshot", "png")?;
+        let file = File::create(&path).map_err(|e| PistonError::CreateFileError {
+            path: path.clone(),
+            source: e,
+        })?;
+        //exec-out keeps the png binary safe, shell would translate line endings on older devices
+        let status = self
+            .command()
+            .args(["exec-out", "screencap", "-p"])
+            .stdout(file)
+            .status()
+            .map_err(|e| PistonError::ADBDevicesError(format!("Failed to run screencap: {}", e)))?;
+        if !status.success() {
+            return Err(PistonError::ADBDevicesError(
+                "screencap failed, is the device unlocked?".to_string(),
+            ));
+        }
+        println!("screenshot saved to {}", path.display());
+        Ok(path)
+    }
+
+    pub fn screen_record(&self, captures_dir: &Path, seconds: u32) -> Result<PathBuf, PistonError> {
+        //screenrecord caps recordings at 3 minutes
+        if seconds == 0 || seconds > 180 {
+            return Err(PistonError::Generic(format!(
+                "screen recordings must be between 1 and 180 seconds, found: {}",
+                seconds
+            )));
+        }
+        let path = self.capture_path(captures_dir, "screenrecord", "mp4")?;
+        let remote = format!("{}/piston-screenrecord.mp4", DEVICE_STAGING_DIR);
+        println!("recording {} for {} seconds...", self.device_id, seconds);
+        self.run(&[
+            "shell",
+            "screenrecord",
+            "--time-limit",
+            &seconds.to_string(),
+            &remote,
+        ])?;
+        self.run(&["pull", &remote, &path.display().to_string()])?;
+        self.run(&["shell", "rm", "-f", &remote])?;
+        println!("screen recording saved to {}", path.display());
+        Ok(path)
+    }
+
+    //copies a local file or directory into the app's data directory, requires a debuggable build
+    pub fn push_to_app(
+        &self,
+        package: &str,
+        local: &Path,
+        remote: &str,
+    ) -> Result<(), PistonError> {
+        let name = local
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or(PistonError::Generic(format!(
+                "Invalid local path: {}",
+                local.display()
+            )))?;
+        let staged = format!("{}/piston-{}", DEVICE_STAGING_DIR, name);
+        self.run(&["push", &local.display().to_string(), &staged])?;
+        let parent = Path::new(remote)
+            .parent()
+            .map(|parent| parent.display().to_string())
+            .filter(|parent| !parent.is_empty())
+            .unwrap_or(".".to_string());
+        let copy = self.run(&[
+            "shell",
+            "run-as",
+            package,
+            "sh",
+            "-c",
+            &format!(
+                "'mkdir -p {} && cp -r {} {}'",
+                Self::quote(&parent),
+                Self::quote(&staged),
+                Self::quote(remote)
+            ),
+        ]);
+        let _ = self.run(&["shell", "rm", "-rf", &staged]);
+        copy?;
+        println!("pushed {} to {}:{}", local.display(), package, remote);
+        Ok(())
+    }
+
+    //copies a file out of the app's data directory, requires a debuggable build
+    pub fn pull_from_app(
+        &self,
+        package: &str,
+        remote: &str,
+        local: &Path,
+    ) -> Result<(), PistonError> {
+        if let Some(parent) = local.parent().filter(|p| !p.as_os_str().is_empty()) {
+            create_dir_all(parent).map_err(|e| PistonError::CreateDirAllError {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+        let file = File::create(local).map_err(|e| PistonError::CreateFileError {
+            path: local.to_path_buf(),
+            source: e,
+        })?;
+        let output = self
+            .command()
+            .args(["exec-out", "run-as", package, "cat", remote])
+            .stdout(file)
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(format!("Failed to run run-as: {}", e)))?;
+        if !output.status.success() {
+            return Err(PistonError::ADBDevicesError(format!(
+                "Failed to pull {} from {}: {}",
+                remote,
+                package,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        println!("pulled {}:{} to {}", package, remote, local.display());
+        Ok(())
+    }
+
+    //device port -> host port, so the app can reach a dev server on the host at localhost
+    pub fn reverse(
+        &self,
+        device_port: u16,
+        host_port: u16,
+        remove: bool,
+    ) -> Result<(), PistonError> {
+        if remove {
+            self.run(&["reverse", "--remove", &format!("tcp:{}", device_port)])?;
+            println!("removed reverse mapping for device port {}", device_port);
+        } else {
+            self.run(&[
+                "reverse",
+                &format!("tcp:{}", device_port),
+                &format!("tcp:{}", host_port),
+            ])?;
+            println!("device tcp:{} -> host tcp:{}", device_port, host_port);
+        }
+        Ok(())
+    }
+
+    //host port -> device port, so host tools can reach a server running in the app
+    pub fn forward(
+        &self,
+        host_port: u16,
+        device_port: u16,
+        remove: bool,
+    ) -> Result<(), PistonError> {
+        if remove {
+            self.run(&["forward", "--remove", &format!("tcp:{}", host_port)])?;
+            println!("removed forward mapping for host port {}", host_port);
+        } else {
+            self.run(&[
+                "forward",
+                &format!("tcp:{}", host_port),
+                &format!("tcp:{}", device_port),
+            ])?;
+            println!("host tcp:{} -> device tcp:{}", host_port, device_port);
+        }
+        Ok(())
+    }
+
+    //paths are double quoted inside the single quoted sh -c script the device shell receives
+    fn quote(arg: &str) -> String {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    }
+}
@@ -1,12 +1,17 @@
 use crate::Helper;
 use crate::PistonError;
+use crate::archive::Archive;
 use crate::devices::AndroidDevice;
-use cargo_metadata::{Metadata, MetadataCommand};
+use crate::elf::Elf;
+use crate::keystore::{Keystore, SigningKey};
+use crate::logcat::Logcat;
+use crate::symbolicate::Symbolizer;
+use cargo_metadata::{Message, Metadata, MetadataCommand};
 use serde::Deserialize;
-use serde_json::Value;
+use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::fs::{File, copy, create_dir_all, remove_file, rename};
-use std::io::{BufWriter, Write};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
@@ -26,8 +31,467 @@ struct AndroidMetadata {
     target_sdk_version: Option<u32>,
     #[serde(default)]
     label: Option<String>,
+    #[serde(default)]
+    activity: Option<String>,
+    #[serde(default)]
+    theme: Option<String>,
+    #[serde(default)]
+    game_activity_libs: Vec<String>,
+    #[serde(default)]
+    java_src: Option<String>,
+    #[serde(default)]
+    java_libs: Vec<String>,
+    #[serde(default)]
+    photo_picker: bool,
+    #[serde(default)]
+    adaptive_icon: AdaptiveIcon,
+    #[serde(default)]
+    build_tools_version: Option<String>,
+    #[serde(default)]
+    ndk_version: Option<String>,
+    //prebuilt .so files to bundle, keyed by abi (arm64-v8a, x86_64)
+    #[serde(default)]
+    native_libs: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    bundle: BundleSettings,
+    //link with -z max-page-size=16384 so LOAD segments support 16 KB page devices
+    #[serde(default = "default_true")]
+    align_16k: bool,
+    //"error", "warn" or "off", defaults to error from target_sdk_version 35
+    #[serde(default)]
+    page_size_check: Option<String>,
+    #[serde(default)]
+    shim: Option<ShimSettings>,
+    //appended to the package of debug builds, e.g. ".debug", so they install next to release builds
+    #[serde(default)]
+    application_id_suffix: Option<String>,
+    //firebase config, turned into string resources like the google-services gradle plugin does
+    #[serde(default)]
+    google_services_json: Option<String>,
+    #[serde(default)]
+    network_security: Option<NetworkSecurity>,
+    //devices the app ships to, defaults to ["phone"]
+    #[serde(default)]
+    form_factors: Vec<FormFactor>,
+    //320x180 Android TV banner, generated from the app icon when not provided
+    #[serde(default)]
+    tv_banner: Option<String>,
+    //subdirectories of assets_path shipped as play asset delivery packs instead of in the base module
+    #[serde(default)]
+    asset_packs: Vec<AssetPack>,
+}
+
+//[[package.metadata.android.asset_packs]]
+#[derive(Deserialize, Clone)]
+struct AssetPack {
+    //module name, also used to request the pack through the AssetPackManager
+    name: String,
+    //directory relative to assets_path, kept as the prefix of the pack's asset paths
+    path: String,
+    #[serde(default)]
+    delivery: AssetPackDelivery,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum AssetPackDelivery {
+    #[default]
+    InstallTime,
+    FastFollow,
+    OnDemand,
+}
+
+impl AssetPack {
+    //bundletool module names follow java identifier rules and may not shadow the base module
+    fn validate(&self) -> Result<(), PistonError> {
+        let mut chars = self.name.chars();
+        let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && self.name != "base";
+        if !valid {
+            return Err(PistonError::ParseManifestError(format!(
+                "Invalid asset pack name: {}, use letters, digits and underscores starting with a letter",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    //every pack becomes its own module directory, so names must be unique, also on case-insensitive file systems
+    fn validate_all(packs: &[AssetPack]) -> Result<(), PistonError> {
+        let mut names: Vec<String> = Vec::new();
+        for pack in packs {
+            pack.validate()?;
+            let name = pack.name.to_lowercase();
+            if names.contains(&name) {
+                return Err(PistonError::ParseManifestError(format!(
+                    "Duplicate asset pack name: {}",
+                    pack.name
+                )));
+            }
+            names.push(name);
+        }
+        Ok(())
+    }
+
+    //asset packs are manifest-only modules, delivery is declared in the dist namespace
+    fn manifest_xml(&self, application_id: &str) -> String {
+        let delivery = match self.delivery {
+            AssetPackDelivery::InstallTime => "install-time",
+            AssetPackDelivery::FastFollow => "fast-follow",
+            AssetPackDelivery::OnDemand => "on-demand",
+        };
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<manifest xmlns:android="http://schemas.android.com/apk/res/android"
+    xmlns:dist="http://schemas.android.com/apk/distribution"
+    package="{}"
+    split="{}">
+    <dist:module dist:type="asset-pack">
+        <dist:fusing dist:include="true" />
+        <dist:delivery>
+            <dist:{} />
+        </dist:delivery>
+    </dist:module>
+</manifest>
+"#,
+            AndroidManifest::escape_xml(application_id),
+            AndroidManifest::escape_xml(&self.name),
+            delivery
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum FormFactor {
+    Phone,
+    Tv,
+    Wear,
+    Chromeos,
+}
+
+//snippets spliced into the manifest template for the configured form factors
+#[derive(Default)]
+struct FormFactorEntries {
+    features: String,
+    application_attrs: String,
+    application_meta: String,
+    activity_attrs: String,
+    categories: String,
+}
+
+//[package.metadata.android.network_security], generated into res/xml/network_security_config.xml
+#[derive(Deserialize, Default, Clone)]
+struct NetworkSecurity {
+    //domains or ip addresses allowed to use cleartext http in every build
+    #[serde(default)]
+    cleartext_domains: Vec<String>,
+    //cleartext domains for debug builds only, e.g. a dev server on the LAN
+    #[serde(default)]
+    debug_cleartext_domains: Vec<String>,
+    #[serde(default)]
+    pins: Vec<DomainPins>,
+    //trust user installed CAs in debug builds, e.g. for a proxy
+    #[serde(default)]
+    debug_overrides: Option<DebugOverrides>,
+}
+
+//[[package.metadata.android.network_security.pins]]
+#[derive(Deserialize, Clone)]
+struct DomainPins {
+    domain: String,
+    #[serde(default)]
+    include_subdomains: bool,
+    //base64 encoded SHA-256 digests of the SubjectPublicKeyInfo, include a backup key
+    sha256: Vec<String>,
+    //yyyy-MM-dd after which the pins are no longer enforced
+    #[serde(default)]
+    expiration: Option<String>,
+}
+
+//[package.metadata.android.network_security.debug_overrides]
+#[derive(Deserialize, Clone)]
+struct DebugOverrides {
+    #[serde(default = "default_true")]
+    trust_user_certs: bool,
+}
+
+impl NetworkSecurity {
+    //debug only entries are left out of release builds entirely
+    fn to_xml(&self, release: bool) -> Result<String, PistonError> {
+        let escape = AndroidManifest::escape_xml;
+        let domains = |domains: &[String], include_subdomains: bool| {
+            domains
+                .iter()
+                .map(|domain| {
+                    format!(
+                        "        <domain includeSubdomains=\"{}\">{}</domain>\n",
+                        include_subdomains,
+                        escape(domain)
+                    )
+                })
+                .collect::<String>()
+        };
+        let mut xml =
+            String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<network-security-config>\n");
+        let mut cleartext = self.cleartext_domains.clone();
+        if !release {
+            cleartext.extend(self.debug_cleartext_domains.iter().cloned());
+        }
+        if !cleartext.is_empty() {
+            xml.push_str("    <domain-config cleartextTrafficPermitted=\"true\">\n");
+            xml.push_str(&domains(&cleartext, false));
+            xml.push_str("    </domain-config>\n");
+        }
+        for pins in &self.pins {
+            if pins.sha256.is_empty() {
+                return Err(PistonError::ParseManifestError(format!(
+                    "network_security pins for {} has no sha256 digests",
+                    pins.domain
+                )));
+            }
+            if pins.sha256.len() == 1 {
+                println!(
+                    "WARNING: {} is pinned to a single key, add a backup pin to survive key rotation",
+                    pins.domain
+                );
+            }
+            xml.push_str("    <domain-config>\n");
+            xml.push_str(&domains(
+                std::slice::from_ref(&pins.domain),
+                pins.include_subdomains,
+            ));
+            match &pins.expiration {
+                Some(expiration) => xml.push_str(&format!(
+                    "        <pin-set expiration=\"{}\">\n",
+                    escape(expiration)
+                )),
+                None => xml.push_str("        <pin-set>\n"),
+            }
+            for digest in &pins.sha256 {
+                xml.push_str(&format!(
+                    "            <pin digest=\"SHA-256\">{}</pin>\n",
+                    escape(digest)
+                ));
+            }
+            xml.push_str("        </pin-set>\n    </domain-config>\n");
+        }
+        if !release && let Some(overrides) = &self.debug_overrides {
+            xml.push_str("    <debug-overrides>\n        <trust-anchors>\n            <certificates src=\"system\" />\n");
+            if overrides.trust_user_certs {
+                xml.push_str("            <certificates src=\"user\" />\n");
+            }
+            xml.push_str("        </trust-anchors>\n    </debug-overrides>\n");
+        }
+        xml.push_str("</network-security-config>\n");
+        Ok(xml)
+    }
+}
+
+//[package.metadata.android.shim], generates the cdylib wrapping the user's crate
+#[derive(Deserialize, Clone)]
+struct ShimSettings {
+    //path of the entry function inside the user's crate, e.g. "android::run"
+    entry: String,
+    //pass the AndroidApp handle to the entry function
+    #[serde(default = "default_true")]
+    pass_app: bool,
+    #[serde(default)]
+    android_activity_version: Option<String>,
+}
+
+//[package.metadata.android.bundle], translated into bundletool's BundleConfig.json
+#[derive(Deserialize, Clone)]
+struct BundleSettings {
+    //glob patterns of files bundletool must store uncompressed, e.g. "assets/**/*.ogg"
+    #[serde(default)]
+    uncompressed_globs: Vec<String>,
+    //store .so files uncompressed and page aligned so they can be mapped straight from the APK
+    #[serde(default = "default_true")]
+    uncompress_native_libs: bool,
+    #[serde(default)]
+    uncompress_dex: Option<bool>,
+    #[serde(default = "default_true")]
+    split_abi: bool,
+    //native-only apps ship no density specific resources besides launcher icons
+    #[serde(default)]
+    split_density: bool,
+    #[serde(default = "default_true")]
+    split_language: bool,
+    //format served to devices without a matching texture#tcf_<format> directory, e.g. "etc2"
+    #[serde(default)]
+    default_texture_format: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for BundleSettings {
+    fn default() -> Self {
+        BundleSettings {
+            uncompressed_globs: Vec::new(),
+            uncompress_native_libs: true,
+            uncompress_dex: None,
+            split_abi: true,
+            split_density: false,
+            split_language: true,
+            default_texture_format: None,
+        }
+    }
+}
+
+impl BundleSettings {
+    //texture_formats are the #tcf_ suffixes found in the bundle's asset directories
+    pub fn to_config(&self, texture_formats: &[String]) -> Result<Value, PistonError> {
+        let split = |value: &str, enabled: bool| {
+            json!({
+                "value": value,
+                "negate": !enabled,
+            })
+        };
+        let mut optimizations = json!({
+            "splitsConfig": {
+                "splitDimension": [
+                    split("ABI", self.split_abi),
+                    split("SCREEN_DENSITY", self.split_density),
+                    split("LANGUAGE", self.split_language),
+                ]
+            },
+            "uncompressNativeLibraries": {
+                "enabled": self.uncompress_native_libs,
+                "alignment": "PAGE_ALIGNMENT_16K",
+            },
+        });
+        if let Some(uncompress_dex) = self.uncompress_dex {
+            optimizations["uncompressDexFiles"] = json!({ "enabled": uncompress_dex });
+        }
+        if !texture_formats.is_empty() {
+            //devices get one format with the suffix stripped, so the app loads textures/ either way
+            let default_format = self.default_texture_format.as_ref().ok_or_else(|| {
+                PistonError::ParseManifestError(format!(
+                    "assets target the texture formats {}, set default_texture_format in [package.metadata.android.bundle]",
+                    texture_formats.join(", ")
+                ))
+            })?;
+            if !texture_formats.contains(default_format) {
+                return Err(PistonError::ParseManifestError(format!(
+                    "default_texture_format {} has no #tcf_{} asset directories, found: {}",
+                    default_format,
+                    default_format,
+                    texture_formats.join(", ")
+                )));
+            }
+            if let Some(dimensions) = optimizations["splitsConfig"]["splitDimension"].as_array_mut()
+            {
+                dimensions.push(json!({
+                    "value": "TEXTURE_COMPRESSION_FORMAT",
+                    "negate": false,
+                    "suffixStripping": {
+                        "enabled": true,
+                        "defaultSuffix": default_format,
+                    },
+                }));
+            }
+        }
+        Ok(json!({
+            "optimizations": optimizations,
+            "compression": {
+                "uncompressedGlob": self.uncompressed_globs,
+            },
+        }))
+    }
+}
+
+//[package.metadata.android.adaptive_icon]
+#[derive(Deserialize, Default, Clone)]
+struct AdaptiveIcon {
+    #[serde(default)]
+    foreground: Option<String>,
+    //hex color such as "#FFFFFF" or a path to an image
+    #[serde(default)]
+    background: Option<String>,
+    #[serde(default)]
+    monochrome: Option<String>,
+}
+
+impl AndroidMetadata {
+    //extract [package.metadata.android] from the root package
+    fn parse(metadata: &Metadata) -> Result<Self, PistonError> {
+        let package = metadata.root_package().ok_or_else(|| {
+            PistonError::ParseManifestError("No Root package found in metadata".to_string())
+        })?;
+        //extract [package.metadata.android] as JSON values
+        let android_meta_value: Value = package
+            .metadata
+            .get("android")
+            .cloned()
+            .unwrap_or(Value::Object(Default::default()));
+        //Deserialize to structured metadata
+        let android_meta: AndroidMetadata =
+            serde_json::from_value(android_meta_value).map_err(|e| {
+                PistonError::ParseManifestError(format!(
+                    "Failed to deserialize [package.metadata.android: {}]",
+                    e
+                ))
+            })?;
+        AssetPack::validate_all(&android_meta.asset_packs)?;
+        Ok(android_meta)
+    }
+
+    //debug builds may be installed next to release builds under a suffixed application id
+    fn application_id(&self, package: &str, release: bool) -> String {
+        match self.application_id_suffix.as_deref() {
+            Some(suffix) if !release => format!("{}{}", package, suffix),
+            _ => package.to_string(),
+        }
+    }
 }
 
+//legacy launcher icon size in px for each mipmap density
+const MIPMAP_DENSITIES: [(&str, u32); 5] = [
+    ("mdpi", 48),
+    ("hdpi", 72),
+    ("xhdpi", 96),
+    ("xxhdpi", 144),
+    ("xxxhdpi", 192),
+];
+
+const LIBCXX_SHARED: &str = "libc++_shared.so";
+
+//build_path subdirectory holding the unstripped libraries of release builds
+const NATIVE_DEBUG_SYMBOLS: &str = "native_debug_symbols";
+
+//libraries every android device provides, which must never be bundled
+const ANDROID_SYSTEM_LIBS: [&str; 22] = [
+    "libc.so",
+    "libm.so",
+    "libdl.so",
+    "liblog.so",
+    "libz.so",
+    "libandroid.so",
+    "libEGL.so",
+    "libGLESv1_CM.so",
+    "libGLESv2.so",
+    "libGLESv3.so",
+    "libvulkan.so",
+    "libOpenSLES.so",
+    "libOpenMAXAL.so",
+    "libaaudio.so",
+    "libamidi.so",
+    "libcamera2ndk.so",
+    "libmediandk.so",
+    "libjnigraphics.so",
+    "libnativewindow.so",
+    "libneuralnetworks.so",
+    "libsync.so",
+    "libbinder_ndk.so",
+];
+
+const NATIVE_ACTIVITY: &str = "android.app.NativeActivity";
+const GAME_ACTIVITY: &str = "com.google.androidgamesdk.GameActivity";
+
 #[derive(Deserialize, Default, Debug)]
 struct AndroidManifest {
     package: String,
@@ -38,6 +502,12 @@ struct AndroidManifest {
     app_label: String,
     app_name: String,
     icon: String,
+    activity: String,
+    theme: Option<String>,
+    photo_picker: bool,
+    has_code: bool,
+    network_security_config: bool,
+    form_factors: Vec<FormFactor>,
 }
 
 impl AndroidManifest {
@@ -50,20 +520,19 @@ impl AndroidManifest {
             PistonError::ParseManifestError("No Root package found in metadata".to_string())
         })?;
         let crate_name = package.name.clone();
-        //extract [package.metadata.android] as JSON values
-        let android_meta_value: Value = package
-            .metadata
-            .get("android")
-            .cloned()
-            .unwrap_or(Value::Object(Default::default()));
-        //Deserialize to structured metadata
-        let android_meta: AndroidMetadata =
-            serde_json::from_value(android_meta_value).map_err(|e| {
-                PistonError::ParseManifestError(format!(
-                    "Failed to deserialize [package.metadata.android: {}]",
-                    e
-                ))
-            })?;
+        let android_meta = AndroidMetadata::parse(metadata)?;
+        let form_factors = if android_meta.form_factors.is_empty() {
+            vec![FormFactor::Phone]
+        } else {
+            android_meta.form_factors.clone()
+        };
+        //play filters out every non watch device for apps requiring android.hardware.type.watch
+        if form_factors.contains(&FormFactor::Wear) && form_factors.len() > 1 {
+            return Err(PistonError::ParseManifestError(
+                "form_factors: wear apps must be built separately from phone, tv and chromeos apps"
+                    .to_string(),
+            ));
+        }
         //Build the manifest with extracted values or defaults
         let mut manifest = Self::default();
         manifest.package = android_meta
@@ -76,46 +545,153 @@ impl AndroidManifest {
         manifest.app_label = android_meta.label.unwrap_or(format!("{}", crate_name));
         manifest.app_name = app_name.to_string();
         manifest.icon = "@mipmap/ic_launcher".to_string();
+        //"native-activity" and "game-activity" mirror the android-activity crate features,
+        //anything else is treated as a fully qualified activity class name
+        manifest.activity = match android_meta.activity.as_deref() {
+            None | Some("native-activity") => NATIVE_ACTIVITY.to_string(),
+            Some("game-activity") => GAME_ACTIVITY.to_string(),
+            Some(class) => class.to_string(),
+        };
+        manifest.theme = android_meta.theme;
+        manifest.photo_picker = android_meta.photo_picker;
+        manifest.network_security_config = android_meta.network_security.is_some();
+        manifest.form_factors = form_factors;
+        //NativeActivity is part of the framework, every other source of code ships a classes.dex
+        manifest.has_code = manifest.activity != NATIVE_ACTIVITY
+            || manifest.photo_picker
+            || android_meta.java_src.is_some()
+            || !android_meta.java_libs.is_empty();
 
         Ok(manifest)
     }
 
-    pub fn to_xml(&self) -> String {
-        let icon_attr = format!(r#" android:icon="{}""#, Self::escape_xml(&self.icon));
+    fn has_form_factor(&self, form_factor: FormFactor) -> bool {
+        self.form_factors.contains(&form_factor)
+    }
+
+    //manifest entries each form factor needs besides the phone defaults
+    fn form_factor_entries(&self) -> FormFactorEntries {
+        let tv = self.has_form_factor(FormFactor::Tv);
+        let mut entries = FormFactorEntries::default();
+        if tv {
+            //leanback is only required when the app ships to nothing but TVs
+            entries.features.push_str(&format!(
+                r#"
+                <uses-feature android:name="android.software.leanback" android:required="{}" />"#,
+                self.form_factors.len() == 1
+            ));
+            entries
+                .application_attrs
+                .push_str(r#" android:banner="@drawable/banner""#);
+            entries.categories.push_str(
+                r#"
+                            <category android:name="android.intent.category.LEANBACK_LAUNCHER" />"#,
+            );
+        }
+        //TVs and chromebooks without a touchscreen would be filtered out by the implied requirement
+        if tv || self.has_form_factor(FormFactor::Chromeos) {
+            entries.features.push_str(
+                r#"
+                <uses-feature android:name="android.hardware.touchscreen" android:required="false" />"#,
+            );
+        }
+        if self.has_form_factor(FormFactor::Chromeos) {
+            entries
+                .activity_attrs
+                .push_str(r#" android:resizeableActivity="true""#);
+        }
+        if self.has_form_factor(FormFactor::Wear) {
+            entries.features.push_str(
+                r#"
+                <uses-feature android:name="android.hardware.type.watch" />"#,
+            );
+            entries.application_meta.push_str(
+                r#"
+                    <meta-data android:name="com.google.android.wearable.standalone" android:value="true" />"#,
+            );
+        }
+        entries
+    }
+
+    //gradle supplies the namespace and sdk versions from build.gradle and rejects them in the source manifest
+    pub fn to_xml(&self, gradle: bool) -> String {
+        let package_attr = if gradle {
+            String::new()
+        } else {
+            format!(
+                "\n                package=\"{}\"",
+                Self::escape_xml(&self.package)
+            )
+        };
+        let uses_sdk = if gradle {
+            String::new()
+        } else {
+            format!(
+                "\n                <uses-sdk android:minSdkVersion=\"{}\" android:targetSdkVersion=\"{}\" />",
+                self.min_sdk_version, self.target_sdk_version
+            )
+        };
+        let icon_attr = format!(
+            r#" android:icon="{}" android:roundIcon="{}_round""#,
+            Self::escape_xml(&self.icon),
+            Self::escape_xml(&self.icon)
+        );
+        let theme_attr = match &self.theme {
+            Some(theme) => format!(r#" android:theme="{}""#, Self::escape_xml(theme)),
+            None => String::new(),
+        };
+        let network_security_attr = if self.network_security_config {
+            r#" android:networkSecurityConfig="@xml/network_security_config""#
+        } else {
+            ""
+        };
+        let form_factor = self.form_factor_entries();
+        let photo_picker = if self.photo_picker {
+            r#"
+                    <activity android:name="com.maverick.photo.PhotoPickerActivity"
+                        android:exported="false" />"#
+        } else {
+            ""
+        };
 
         format!(
             r#"<?xml version="1.0" encoding="utf-8"?>
-            <manifest xmlns:android="http://schemas.android.com/apk/res/android"
-                package="{package}"
+            <manifest xmlns:android="http://schemas.android.com/apk/res/android"{package_attr}
                 android:versionCode="{version_code}"
                 android:versionName="{version_name}">
-
-                <uses-sdk android:minSdkVersion="{min_sdk}" android:targetSdkVersion="{target_sdk}" />
+{uses_sdk}
                 <uses-permission android:name="android.permission.CAMERA" />
-                <uses-feature android:name="android.hardware.camera" android:required="false" />
+                <uses-feature android:name="android.hardware.camera" android:required="false" />{features}
 
-                <application android:label="{label}" android:hasCode="true"{icon_attr}>
-                    <activity android:name="android.app.NativeActivity"
+                <application android:label="{label}" android:hasCode="{has_code}"{icon_attr}{network_security_attr}{application_attrs}>{application_meta}
+                    <activity android:name="{activity}"
                         android:label="{label}"
-                        android:exported="true">
+                        android:exported="true"{theme_attr}{activity_attrs}>
                         <meta-data android:name="android.app.lib_name" android:value="{app_name}" />
                         <intent-filter>
                             <action android:name="android.intent.action.MAIN" />
-                            <category android:name="android.intent.category.LAUNCHER" />
+                            <category android:name="android.intent.category.LAUNCHER" />{categories}
                         </intent-filter>
-                    </activity>
-                    <activity android:name="com.maverick.photo.PhotoPickerActivity"
-                        android:exported="false" />
+                    </activity>{photo_picker}
                 </application>
             </manifest>"#,
-            package = Self::escape_xml(&self.package),
+            package_attr = package_attr,
             version_code = self.version_code,
             version_name = Self::escape_xml(&self.version_name),
-            min_sdk = self.min_sdk_version,
-            target_sdk = self.target_sdk_version,
+            uses_sdk = uses_sdk,
             label = Self::escape_xml(&self.app_label),
             app_name = Self::escape_xml(&self.app_name), // Using app_name for lib_name in meta-data
             icon_attr = icon_attr,
+            activity = Self::escape_xml(&self.activity),
+            theme_attr = theme_attr,
+            has_code = self.has_code,
+            network_security_attr = network_security_attr,
+            features = form_factor.features,
+            application_attrs = form_factor.application_attrs,
+            application_meta = form_factor.application_meta,
+            activity_attrs = form_factor.activity_attrs,
+            categories = form_factor.categories,
+            photo_picker = photo_picker,
         )
     }
 
@@ -125,9 +701,11 @@ impl AndroidManifest {
         })?;
 
         let mut writer = BufWriter::new(file);
-        writer.write_all(self.to_xml().as_bytes()).map_err(|e| {
-            PistonError::WriteManifestError(format!("Failed to write manifest file: {}", e))
-        })?;
+        writer
+            .write_all(self.to_xml(false).as_bytes())
+            .map_err(|e| {
+                PistonError::WriteManifestError(format!("Failed to write manifest file: {}", e))
+            })?;
 
         Ok(())
     }
@@ -149,9 +727,8 @@ pub struct AndroidBuilder {
     output_path: Option<PathBuf>,
     icon_path: String,
     assets: String,
-    key_path: String,
-    key_pass: String,
-    key_alias: String,
+    keystore: Keystore,
+    application_id: String,
     app_name: String,
     lib_name: String,
     manifest: AndroidManifest,
@@ -162,13 +739,26 @@ pub struct AndroidBuilder {
     resources: PathBuf,
     build_tools_version: String,
     bundletool_path: String,
-    common_name: String,
-    org_unit: String,
-    org: String,
-    locality: String,
-    state: String,
-    country: String,
     device_target: Option<AndroidDevice>,
+    game_activity_libs: Vec<String>,
+    java_src: Option<PathBuf>,
+    java_libs: Vec<String>,
+    kotlinc_path: Option<String>,
+    adaptive_icon: AdaptiveIcon,
+    android_jar: PathBuf,
+    native_libs: HashMap<String, Vec<String>>,
+    bundle_settings: BundleSettings,
+    align_16k: bool,
+    page_size_check: String,
+    shim: Option<ShimSettings>,
+    user_lib_name: String,
+    //android-activity version the user's crate resolves, pinned in the shim
+    android_activity_version: Option<String>,
+    google_services_json: Option<PathBuf>,
+    network_security: Option<NetworkSecurity>,
+    tv_banner: Option<PathBuf>,
+    asset_packs: Vec<AssetPack>,
+    application_id_suffix: Option<String>,
 }
 
 impl AndroidBuilder {
@@ -178,7 +768,7 @@ impl AndroidBuilder {
         cwd: PathBuf,
         env_vars: HashMap<String, String>,
         device_target: Option<AndroidDevice>,
-    ) -> Result<(PathBuf, String, String), PistonError> {
+    ) -> Result<(PathBuf, String, String, String, SigningKey), PistonError> {
         println!("building for android");
         let mut op = AndroidBuilder::new(release, target, cwd, env_vars, device_target)?;
 
@@ -191,8 +781,45 @@ impl AndroidBuilder {
         //>>Postbuild
         op.post_build(aab_path)?;
 
-        //return bundle output path and app name
-        Ok((op.output_path.unwrap(), op.app_name, op.manifest.package))
+        //return bundle output path, app name, application id, launch activity and signing key
+        Ok((
+            op.output_path.unwrap(),
+            op.app_name,
+            op.application_id,
+            op.manifest.activity,
+            op.keystore.key,
+        ))
+    }
+
+    //gradle project at out_dir wrapping the same manifest, resources, assets and native libs as a build
+    pub fn export(
+        release: bool,
+        target: String,
+        cwd: PathBuf,
+        env_vars: HashMap<String, String>,
+        out_dir: PathBuf,
+    ) -> Result<(), PistonError> {
+        println!("exporting android gradle project to {}", out_dir.display());
+        //the other build type is only signed when its key already exists
+        let other_keystore = Keystore::from_hm(&env_vars, !release)?;
+        let mut op = AndroidBuilder::new(release, target, cwd, env_vars, None)?;
+        op.pre_build()?;
+        op.build_so()?;
+        op.keystore.ensure()?;
+        let other_key = (Path::new(&other_keystore.key.path).exists()
+            && other_keystore.has_alias().unwrap_or(false))
+        .then_some(&other_keystore.key);
+        let (debug_key, release_key) = if release {
+            (other_key, Some(&op.keystore.key))
+        } else {
+            (Some(&op.keystore.key), other_key)
+        };
+        op.write_gradle_project(&out_dir, debug_key, release_key)?;
+        println!(
+            "✅ gradle project exported to {}, open it in Android Studio to build and run it",
+            out_dir.display()
+        );
+        Ok(())
     }
 
     fn new(
@@ -209,84 +836,75 @@ impl AndroidBuilder {
             cwd
         );
         //parse env vars
-        let ndk_path: &String = Helper::get_or_err(&env_vars, "ndk_path")?;
-        let sdk_path: &String = Helper::get_or_err(&env_vars, "sdk_path")?;
         let java_path: &String = Helper::get_or_err(&env_vars, "java_path")?;
         let bundletool_path: &String = Helper::get_or_err(&env_vars, "bundletool_path")?;
-        let build_tools_version: String = Helper::get_build_tools_version(&sdk_path)?;
-        //obtain default path for keystore
-        let user_output = Command::new("whoami")
-            .output()
-            .map_err(|e| PistonError::WhoAmIError(format!("Failed to run 'whoami': {}", e)))?;
-
-        if !user_output.status.success() {
-            return Err(PistonError::WhoAmIError(format!(
-                "Failed to run 'whoami': {}",
-                String::from_utf8_lossy(&user_output.stderr)
-            )));
-        }
-        let user = String::from_utf8_lossy(&user_output.stdout)
-            .trim()
-            .to_string();
-        if user.is_empty() {
-            return Err(PistonError::WhoAmIError(format!(
-                "Failed to obtain user id with whoami: {}",
-                String::from_utf8_lossy(&user_output.stderr)
-            )));
-        }
-        let default_path = format!("/Users/{}/.android/release.keystore", user);
-        //allow .env to override default key_path and key_pass and key_alias if it exists
-        let key_path: String = env_vars
-            .get("aab_release_key")
-            .cloned()
-            .unwrap_or(default_path);
-        let key_pass: String = env_vars
-            .get("aab_key_pass")
-            .cloned()
-            .unwrap_or("piston".to_string());
-        let key_alias: String = env_vars
-            .get("aab_key_alias")
-            .cloned()
-            .unwrap_or("release-key".to_string());
-        //allow .env to ovverride default dname metadata if provided
-        let common_name: String = env_vars
-            .get("common_name")
-            .cloned()
-            .unwrap_or("Unknown".to_string());
-        let org_unit: String = env_vars
-            .get("org_unit")
-            .cloned()
-            .unwrap_or("Development".to_string());
-        let org: String = env_vars
-            .get("org")
-            .cloned()
-            .unwrap_or("Unknown".to_string());
-        let locality: String = env_vars
-            .get("locality")
-            .cloned()
-            .unwrap_or("Unknown".to_string());
-        let state: String = env_vars
-            .get("state")
-            .cloned()
-            .unwrap_or("Unknown".to_string());
-        let country: String = env_vars
-            .get("country")
-            .filter(|s| s.trim().len() == 2)
-            .map(|s| s.trim().to_uppercase().to_string())
-            .unwrap_or_else(|| "US".to_string());
+        let kotlinc_path: Option<String> = env_vars.get("kotlinc_path").cloned();
+        let keystore = Keystore::from_hm(&env_vars, release)?;
         //parse cargo.toml
         let metadata: Metadata = MetadataCommand::new()
             .current_dir(cwd.clone())
             .exec()
             .map_err(|e| PistonError::CargoParseError(e.to_string()))?;
 
-        let lib_name = Helper::get_lib_name(&metadata)?;
+        let mut lib_name = Helper::get_lib_name(&metadata)?;
         let icon_path = Helper::get_icon_path(&metadata, &cwd);
         let assets = Helper::get_assets_path(&metadata);
         let app_name = Helper::get_app_name(&metadata)?;
         let app_version = Helper::get_app_version(&metadata)?;
+        let android_meta = AndroidMetadata::parse(&metadata)?;
         //generate androidmanifest.xml
-        let manifest = AndroidManifest::build(&metadata, &app_name, &app_version)?;
+        let mut manifest = AndroidManifest::build(&metadata, &app_name, &app_version)?;
+        //in shim mode the generated wrapper crate is the cdylib that NativeActivity loads
+        let mut user_lib_name = lib_name.clone();
+        if android_meta.shim.is_some() {
+            user_lib_name = Helper::get_rlib_name(&metadata)?;
+            lib_name = format!("{}_shim", user_lib_name);
+            manifest.app_name = lib_name.clone();
+        }
+        //resolve sdk & ndk installations, honoring versions pinned in [package.metadata.android]
+        let sdk_path: String = Helper::get_sdk_path(&env_vars)?;
+        let ndk_path: String =
+            Helper::get_ndk_path(&env_vars, &sdk_path, android_meta.ndk_version.as_deref())?;
+        let build_tools_version: String = Helper::get_build_tools_version(
+            &sdk_path,
+            android_meta.build_tools_version.as_deref(),
+            manifest.target_sdk_version,
+        )?;
+        let android_jar: PathBuf = Helper::get_android_jar(&sdk_path, manifest.target_sdk_version)?;
+        //google play requires 16 KB page support for apps targeting android 15+
+        let page_size_check = match android_meta.page_size_check.as_deref() {
+            Some(check @ ("error" | "warn" | "off")) => check.to_string(),
+            Some(other) => {
+                return Err(PistonError::AndroidConfigError(format!(
+                    "page_size_check must be one of error, warn or off, found: {}",
+                    other
+                )));
+            }
+            None if manifest.target_sdk_version >= 35 => "error".to_string(),
+            None => "warn".to_string(),
+        };
+        println!(
+            "using sdk: {}, ndk: {}, build-tools: {}, platform: {}",
+            sdk_path,
+            ndk_path,
+            build_tools_version,
+            android_jar.display()
+        );
+        //refuse to build for a device that could not install the result
+        if let Some(device) = &device_target
+            && let Some(device_sdk) = device.sdk
+            && device_sdk < manifest.min_sdk_version
+        {
+            return Err(PistonError::InstallAPKError(format!(
+                "device {} runs sdk {} but min_sdk_version is {}",
+                device.id, device_sdk, manifest.min_sdk_version
+            )));
+        }
+        if manifest.activity == GAME_ACTIVITY && android_meta.game_activity_libs.is_empty() {
+            return Err(PistonError::AndroidConfigError(
+                "activity is set to game-activity but no game_activity_libs were provided in [package.metadata.android]".to_string(),
+            ));
+        }
         let build_path: PathBuf = cwd
             .join("target")
             .join(if release { "release" } else { "debug" })
@@ -294,7 +912,7 @@ impl AndroidBuilder {
             .join("androidbuilder");
         println!("build path: {:?}", build_path);
         //empty dirs all build_path
-        Helper::empty_directory(build_path.as_path(), &["assets"])?;
+        Helper::empty_directory(build_path.as_path(), &["assets", "asset_packs"])?;
         //mkdir all build_path
         create_dir_all(&build_path).map_err(|e| PistonError::CreateDirAllError {
             path: build_path.clone(),
@@ -305,6 +923,12 @@ impl AndroidBuilder {
         let resources_path: PathBuf = build_path.join("app").join("src").join("main").join("res");
         //write AndroidManifest.xml to file
         manifest.write_to(&manifest_path.as_path())?;
+        let application_id = android_meta.application_id(&manifest.package, release);
+        //java sources are resolved relative to the project root
+        let java_src: Option<PathBuf> = android_meta.java_src.map(|src| cwd.join(src));
+        let google_services_json: Option<PathBuf> =
+            android_meta.google_services_json.map(|path| cwd.join(path));
+        let tv_banner: Option<PathBuf> = android_meta.tv_banner.map(|path| cwd.join(path));
         Ok(AndroidBuilder {
             release: release,
             target: target.to_string(),
@@ -313,26 +937,37 @@ impl AndroidBuilder {
             output_path: None,
             icon_path: icon_path,
             assets: assets,
-            key_path: key_path,
-            key_pass: key_pass,
-            key_alias: key_alias,
+            keystore,
+            application_id,
             app_name: app_name,
             lib_name: lib_name,
             manifest: manifest,
             manifest_path: manifest_path,
-            ndk_path: ndk_path.to_string(),
-            sdk_path: sdk_path.to_string(),
+            ndk_path,
+            sdk_path,
             java_path: java_path.to_string(),
             resources: resources_path,
             build_tools_version: build_tools_version,
             bundletool_path: bundletool_path.to_string(),
-            common_name: common_name,
-            org_unit: org_unit,
-            org: org,
-            locality: locality,
-            state: state,
-            country: country,
             device_target: device_target,
+            game_activity_libs: android_meta.game_activity_libs,
+            java_src,
+            java_libs: android_meta.java_libs,
+            kotlinc_path,
+            adaptive_icon: android_meta.adaptive_icon,
+            android_jar,
+            native_libs: android_meta.native_libs,
+            bundle_settings: android_meta.bundle,
+            align_16k: android_meta.align_16k,
+            page_size_check,
+            shim: android_meta.shim,
+            user_lib_name,
+            android_activity_version: Self::resolved_android_activity(&metadata),
+            google_services_json,
+            network_security: android_meta.network_security,
+            tv_banner,
+            asset_packs: android_meta.asset_packs,
+            application_id_suffix: android_meta.application_id_suffix,
         })
     }
 
@@ -358,104 +993,454 @@ impl AndroidBuilder {
         if self.output_path.as_ref().is_none() {
             return Err(PistonError::Generic("output path not provided".to_string()));
         }
-        //establish absolute paths for  mipmap dirs
-        let hdpi_path: PathBuf = self.resources.join("mipmap-hdpi");
-        let mdpi_path: PathBuf = self.resources.join("mipmap-mdpi");
-        let xhdpi_path: PathBuf = self.resources.join("mipmap-xhdpi");
-        let xxhdpi_path: PathBuf = self.resources.join("mipmap-xxhdpi");
-        let xxxhdpi_path: PathBuf = self.resources.join("mipmap-xxxhdpi");
-        //create mipmap dirs
-        create_dir_all(&hdpi_path).map_err(|e| PistonError::CreateDirAllError {
-            path: hdpi_path.clone(),
-            source: e,
-        })?;
-        create_dir_all(&mdpi_path).map_err(|e| PistonError::CreateDirAllError {
-            path: mdpi_path.clone(),
-            source: e,
-        })?;
-        create_dir_all(&xhdpi_path).map_err(|e| PistonError::CreateDirAllError {
-            path: xhdpi_path.clone(),
-            source: e,
+        //generate legacy, round and adaptive launcher icons
+        self.generate_icons()?;
+        //generate the android_main wrapper crate if configured
+        self.generate_shim()?;
+        //generate the firebase string resources if configured
+        self.generate_google_services()?;
+        //generate the network security config if configured
+        if let Some(network_security) = &self.network_security {
+            let xml_path = self.resources.join("xml");
+            create_dir_all(&xml_path).map_err(|e| PistonError::CreateDirAllError {
+                path: xml_path.clone(),
+                source: e,
+            })?;
+            Helper::write_file(
+                &xml_path.join("network_security_config.xml"),
+                &network_security.to_xml(self.release)?,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn generate_google_services(&self) -> Result<(), PistonError> {
+        let json_path = match &self.google_services_json {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        println!("processing {}", json_path.display());
+        let contents = std::fs::read_to_string(json_path).map_err(|e| {
+            PistonError::Generic(format!("Failed to read {}: {}", json_path.display(), e))
         })?;
-        create_dir_all(&xxhdpi_path).map_err(|e| PistonError::CreateDirAllError {
-            path: xxhdpi_path.clone(),
-            source: e,
+        let json: Value = serde_json::from_str(&contents).map_err(|e| {
+            PistonError::ParseManifestError(format!(
+                "Invalid google_services_json {}: {}",
+                json_path.display(),
+                e
+            ))
         })?;
-        create_dir_all(&xxxhdpi_path).map_err(|e| PistonError::CreateDirAllError {
-            path: xxxhdpi_path.clone(),
+        let values_xml = Self::google_services_values(&json, &self.application_id)?;
+        let values_path = self.resources.join("values");
+        create_dir_all(&values_path).map_err(|e| PistonError::CreateDirAllError {
+            path: values_path.clone(),
             source: e,
         })?;
-        //convert icon to various mipmaps
-        let hdpi_target: PathBuf = hdpi_path.join("ic_launcher.png");
-        Helper::resize_png(
-            &self.icon_path.as_ref(),
-            &hdpi_target.display().to_string(),
-            48,
-            48,
-        )?;
-        let mdpi_target: PathBuf = mdpi_path.join("ic_launcher.png");
-        Helper::resize_png(
-            &self.icon_path.as_ref(),
-            &mdpi_target.display().to_string(),
-            72,
-            72,
-        )?;
-        let xhdpi_target: PathBuf = xhdpi_path.join("ic_launcher.png");
-        Helper::resize_png(
-            &self.icon_path.as_ref(),
-            &xhdpi_target.display().to_string(),
-            96,
-            96,
-        )?;
-        let xxhdpi_target: PathBuf = xxhdpi_path.join("ic_launcher.png");
-        Helper::resize_png(
-            &self.icon_path.as_ref(),
-            &xxhdpi_target.display().to_string(),
-            144,
-            144,
-        )?;
-        let xxxhdpi_target: PathBuf = xxxhdpi_path.join("ic_launcher.png");
-        Helper::resize_png(
-            &self.icon_path.as_ref(),
-            &xxxhdpi_target.display().to_string(),
-            192,
-            192,
-        )?;
+        Helper::write_file(&values_path.join("values.xml"), &values_xml)?;
         Ok(())
     }
 
-    fn build(&mut self) -> Result<PathBuf, PistonError> {
-        println!("building for android");
-        //build the android .so with cargo
-        self.build_so()?;
-        //compile the resources directory
-        let resources = self.compile_resources()?;
-        //Link manifest and resources (aapt2 link)
-        let base_dir = self.build_path.join("base");
-        //empty the dir if it exists
-        Helper::empty_directory(&base_dir, &["assets"])?;
-        create_dir_all(&base_dir).map_err(|e| PistonError::CreateDirAllError {
-            path: base_dir.clone(),
-            source: e,
-        })?;
-        //link manifest and resources with aapt2
-        self.link_manifest_and_resources(&resources, &base_dir)?;
-        //add assets if any (copy to base/asssets)
-        let bind = &self.assets.clone();
-        let assets_src = Path::new(&bind);
-        let assets_base = &base_dir.join("assets");
-        Helper::sync_assets(&assets_src, &assets_base)?;
-        //add the .so lib for a single lib
-        self.add_lib(&base_dir, self.target.as_ref())?;
-        //add the photo picker activity dex
-        self.add_activity_dex(&base_dir)?;
-        //zip base module
-        let base_zip = self.build_path.join("base.zip");
-        self.zip_base(&base_dir)?;
+    //the string resources the google-services gradle plugin generates for the client matching the application id
+    fn google_services_values(json: &Value, application_id: &str) -> Result<String, PistonError> {
+        let clients = json["client"].as_array().cloned().unwrap_or_default();
+        let client = clients
+            .iter()
+            .find(|client| {
+                client["client_info"]["android_client_info"]["package_name"].as_str()
+                    == Some(application_id)
+            })
+            .ok_or_else(|| {
+                let packages: Vec<&str> = clients
+                    .iter()
+                    .filter_map(|client| {
+                        client["client_info"]["android_client_info"]["package_name"].as_str()
+                    })
+                    .collect();
+                PistonError::ParseManifestError(format!(
+                    "No client for {} in google_services_json, found: {}",
+                    application_id,
+                    packages.join(", ")
+                ))
+            })?;
+        let project = &json["project_info"];
+        //web client ids have client_type 3
+        fn web_client_id(clients: &Value) -> Option<&str> {
+            clients
+                .as_array()?
+                .iter()
+                .find(|oauth| oauth["client_type"].as_u64() == Some(3))?["client_id"]
+                .as_str()
+        }
+        let api_key = client["api_key"][0]["current_key"].as_str();
+        let strings: Vec<(&str, Option<&str>)> = vec![
+            (
+                "google_app_id",
+                client["client_info"]["mobilesdk_app_id"].as_str(),
+            ),
+            ("gcm_defaultSenderId", project["project_number"].as_str()),
+            ("project_id", project["project_id"].as_str()),
+            ("google_storage_bucket", project["storage_bucket"].as_str()),
+            ("firebase_database_url", project["firebase_url"].as_str()),
+            ("google_api_key", api_key),
+            ("google_crash_reporting_api_key", api_key),
+            (
+                "default_web_client_id",
+                web_client_id(&client["oauth_client"]).or(web_client_id(
+                    &client["services"]["appinvite_service"]["other_platform_oauth_client"],
+                )),
+            ),
+        ];
+        if strings[0].1.is_none() {
+            return Err(PistonError::ParseManifestError(format!(
+                "No mobilesdk_app_id for {} in google_services_json",
+                application_id
+            )));
+        }
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<resources>\n");
+        for (name, value) in strings {
+            if let Some(value) = value {
+                xml.push_str(&format!(
+                    "    <string name=\"{}\" translatable=\"false\">{}</string>\n",
+                    name,
+                    AndroidManifest::escape_xml(value)
+                ));
+            }
+        }
+        xml.push_str("</resources>\n");
+        Ok(xml)
+    }
+
+    fn generate_icons(&self) -> Result<(), PistonError> {
+        println!("generating launcher icons");
+        let foreground = self
+            .adaptive_icon
+            .foreground
+            .as_ref()
+            .map(|path| self.cwd.join(path).display().to_string());
+        let monochrome = self
+            .adaptive_icon
+            .monochrome
+            .as_ref()
+            .map(|path| self.cwd.join(path).display().to_string());
+        //the background is either a hex color or an image path
+        let background = self
+            .adaptive_icon
+            .background
+            .clone()
+            .unwrap_or("#FFFFFF".to_string());
+        let background_color = Helper::is_hex_color(&background);
+        let background_image = self.cwd.join(&background).display().to_string();
+
+        for (density, size) in MIPMAP_DENSITIES {
+            let mipmap_path: PathBuf = self.resources.join(format!("mipmap-{}", density));
+            create_dir_all(&mipmap_path).map_err(|e| PistonError::CreateDirAllError {
+                path: mipmap_path.clone(),
+                source: e,
+            })?;
+            //legacy square and round icons
+            Helper::resize_png(
+                &self.icon_path,
+                &mipmap_path.join("ic_launcher.png").display().to_string(),
+                size,
+                size,
+            )?;
+            Helper::round_png(
+                &self.icon_path,
+                &mipmap_path
+                    .join("ic_launcher_round.png")
+                    .display()
+                    .to_string(),
+                size,
+            )?;
+            //adaptive layers are 108dp, of which the inner 72dp is the visible area
+            let layer_size = size * 108 / 48;
+            let foreground_target = mipmap_path
+                .join("ic_launcher_foreground.png")
+                .display()
+                .to_string();
+            match &foreground {
+                Some(path) => Helper::resize_png(path, &foreground_target, layer_size, layer_size)?,
+                None => Helper::pad_png(
+                    &self.icon_path,
+                    &foreground_target,
+                    layer_size,
+                    size * 72 / 48,
+                )?,
+            }
+            if !background_color {
+                Helper::resize_png(
+                    &background_image,
+                    &mipmap_path
+                        .join("ic_launcher_background.png")
+                        .display()
+                        .to_string(),
+                    layer_size,
+                    layer_size,
+                )?;
+            }
+            if let Some(path) = &monochrome {
+                Helper::resize_png(
+                    path,
+                    &mipmap_path
+                        .join("ic_launcher_monochrome.png")
+                        .display()
+                        .to_string(),
+                    layer_size,
+                    layer_size,
+                )?;
+            }
+        }
+
+        let background_drawable = if background_color {
+            let values_path = self.resources.join("values");
+            create_dir_all(&values_path).map_err(|e| PistonError::CreateDirAllError {
+                path: values_path.clone(),
+                source: e,
+            })?;
+            let color_xml = format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<resources>
+    <color name="ic_launcher_background">{}</color>
+</resources>
+"#,
+                background
+            );
+            Helper::write_file(&values_path.join("ic_launcher_background.xml"), &color_xml)?;
+            "@color/ic_launcher_background"
+        } else {
+            "@mipmap/ic_launcher_background"
+        };
+        let monochrome_layer = if monochrome.is_some() {
+            "\n    <monochrome android:drawable=\"@mipmap/ic_launcher_monochrome\" />"
+        } else {
+            ""
+        };
+        let adaptive_xml = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<adaptive-icon xmlns:android="http://schemas.android.com/apk/res/android">
+    <background android:drawable="{}" />
+    <foreground android:drawable="@mipmap/ic_launcher_foreground" />{}
+</adaptive-icon>
+"#,
+            background_drawable, monochrome_layer
+        );
+        let anydpi_path = self.resources.join("mipmap-anydpi-v26");
+        create_dir_all(&anydpi_path).map_err(|e| PistonError::CreateDirAllError {
+            path: anydpi_path.clone(),
+            source: e,
+        })?;
+        Helper::write_file(&anydpi_path.join("ic_launcher.xml"), &adaptive_xml)?;
+        Helper::write_file(&anydpi_path.join("ic_launcher_round.xml"), &adaptive_xml)?;
+        if self.manifest.has_form_factor(FormFactor::Tv) {
+            self.generate_tv_banner(background_color.then_some(background.as_str()))?;
+        }
+        Ok(())
+    }
+
+    //TV launchers show a 320x180 px xhdpi banner instead of the launcher icon
+    fn generate_tv_banner(&self, background: Option<&str>) -> Result<(), PistonError> {
+        let drawable_path = self.resources.join("drawable-xhdpi");
+        create_dir_all(&drawable_path).map_err(|e| PistonError::CreateDirAllError {
+            path: drawable_path.clone(),
+            source: e,
+        })?;
+        let banner_target = drawable_path.join("banner.png").display().to_string();
+        match &self.tv_banner {
+            Some(path) => Helper::resize_png(&path.display().to_string(), &banner_target, 320, 180),
+            //center the icon on the adaptive icon background color
+            None => Helper::banner_png(&self.icon_path, &banner_target, 320, 180, background),
+        }
+    }
+
+    //target/piston/android-shim, a cdylib depending on the user's crate that exports android_main
+    fn shim_dir(&self) -> PathBuf {
+        self.cwd.join("target").join("piston").join("android-shim")
+    }
+
+    //android-activity as resolved for the root package, a direct dependency first, then the only
+    //version in the graph (e.g. through winit)
+    fn resolved_android_activity(metadata: &Metadata) -> Option<String> {
+        let resolve = metadata.resolve.as_ref()?;
+        let root = resolve.root.as_ref()?;
+        let direct = resolve
+            .nodes
+            .iter()
+            .find(|node| &node.id == root)?
+            .deps
+            .iter()
+            .map(|dep| &metadata[&dep.pkg])
+            .find(|package| package.name == "android-activity");
+        if let Some(package) = direct {
+            return Some(package.version.to_string());
+        }
+        let mut versions: Vec<String> = metadata
+            .packages
+            .iter()
+            .filter(|package| package.name == "android-activity")
+            .map(|package| package.version.to_string())
+            .collect();
+        versions.sort();
+        versions.dedup();
+        match versions.as_slice() {
+            [version] => Some(version.clone()),
+            _ => None,
+        }
+    }
+
+    fn generate_shim(&self) -> Result<(), PistonError> {
+        let shim = match &self.shim {
+            Some(shim) => shim,
+            None => return Ok(()),
+        };
+        let shim_dir = self.shim_dir();
+        println!("generating android_main shim crate at {:?}", shim_dir);
+        create_dir_all(&shim_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: shim_dir.clone(),
+            source: e,
+        })?;
+        let user_crate = &self.user_lib_name;
+        let activity_feature = if self.manifest.activity == GAME_ACTIVITY {
+            "game-activity"
+        } else {
+            "native-activity"
+        };
+        let escape_toml = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        //the AndroidApp handed to the user's entry has to come from the very same android-activity
+        let activity_version = match (
+            &self.android_activity_version,
+            &shim.android_activity_version,
+        ) {
+            (Some(resolved), configured) => {
+                if let Some(configured) = configured
+                    && !resolved.starts_with(configured.as_str())
+                {
+                    println!(
+                        "⚠️  ignoring android_activity_version {}, your crate resolves android-activity {}",
+                        configured, resolved
+                    );
+                }
+                format!("={}", resolved)
+            }
+            (None, Some(configured)) => configured.clone(),
+            (None, None) => "0.6".to_string(),
+        };
+        let cargo_toml = format!(
+            r#"# generated by cargo-piston, do not edit
+[package]
+name = "{lib_name}"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[lib]
+name = "{lib_name}"
+path = "lib.rs"
+crate-type = ["cdylib"]
+
+[dependencies]
+{user_crate} = {{ package = "{package}", path = "{path}" }}
+android-activity = {{ version = "{activity_version}", features = ["{activity_feature}"] }}
+
+[workspace]
+"#,
+            lib_name = self.lib_name,
+            user_crate = user_crate,
+            package = escape_toml(&self.app_name),
+            path = escape_toml(&self.cwd.display().to_string()),
+            activity_version = escape_toml(&activity_version),
+            activity_feature = activity_feature,
+        );
+        Helper::write_file(&shim_dir.join("Cargo.toml"), &cargo_toml)?;
+
+        //entry paths are relative to the user's crate root
+        let entry = shim
+            .entry
+            .trim_start_matches("crate::")
+            .trim_start_matches(&format!("{}::", user_crate));
+        let call = if shim.pass_app {
+            format!("{}::{}(app);", user_crate, entry)
+        } else {
+            format!("let _ = app;\n    {}::{}();", user_crate, entry)
+        };
+        let lib_rs = format!(
+            r#"// generated by cargo-piston, do not edit
+use android_activity::AndroidApp;
+
+#[unsafe(no_mangle)]
+pub extern "C" fn android_main(app: AndroidApp) {{
+    {}
+}}
+"#,
+            call
+        );
+        Helper::write_file(&shim_dir.join("lib.rs"), &lib_rs)?;
+        Ok(())
+    }
+
+    fn build(&mut self) -> Result<PathBuf, PistonError> {
+        println!("building for android");
+        //build the android .so with cargo
+        self.build_so()?;
+        //compile the resources directory
+        let resources = self.compile_resources()?;
+        //Link manifest and resources (aapt2 link)
+        let base_dir = self.build_path.join("base");
+        //empty the dir if it exists
+        Helper::empty_directory(&base_dir, &["assets"])?;
+        create_dir_all(&base_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: base_dir.clone(),
+            source: e,
+        })?;
+        //link manifest and resources with aapt2
+        self.link_manifest_and_resources(&resources, &base_dir)?;
+        //add assets if any (copy to base/asssets)
+        let bind = &self.assets.clone();
+        let assets_src = Path::new(&bind);
+        let assets_base = &base_dir.join("assets");
+        //asset pack directories are left out of the base module
+        let pack_dirs: Vec<PathBuf> = self
+            .asset_packs
+            .iter()
+            .map(|pack| assets_src.join(&pack.path))
+            .collect();
+        Helper::sync_assets(&assets_src, &assets_base, &pack_dirs)?;
+        //add the .so lib for a single lib
+        let bundled = self.add_lib(&base_dir, self.target.as_ref())?;
+        //release bundles ship stripped libraries, the symbols go to native-debug-symbols.zip
+        if self.release {
+            let abi = Self::android_abi(&self.target)?;
+            self.strip_libs(abi, &base_dir.join("lib").join(abi), &bundled)?;
+        }
+        //zip the unstripped libraries of release builds
+        let debug_symbols = if self.release {
+            self.native_debug_symbols()?
+        } else {
+            Vec::new()
+        };
+        //add the java helpers, activity classes and user sources as classes.dex
+        self.add_dex(&base_dir)?;
+        //zip base module
+        let base_zip = self.build_path.join("base.zip");
+        self.zip_module(&base_dir, &base_zip)?;
+        //build and zip the asset pack modules
+        let mut modules = vec![base_zip];
+        modules.extend(self.build_asset_packs()?);
+        //collect the texture compression formats targeted by the base module and the asset packs
+        let mut texture_formats = Helper::texture_compression_formats(assets_base)?;
+        for pack in &self.asset_packs {
+            let pack_assets = self
+                .build_path
+                .join("asset_packs")
+                .join(&pack.name)
+                .join("assets");
+            texture_formats.extend(Helper::texture_compression_formats(&pack_assets)?);
+        }
+        texture_formats.sort();
+        texture_formats.dedup();
         //build AAB with bundletool
         let output_bind = self.output_path.clone().unwrap();
         let aab_path = output_bind.join(format!("{}.aab", self.app_name));
-        self.build_bundle(&base_zip, &aab_path)?;
+        self.build_bundle(&modules, &texture_formats, &debug_symbols, &aab_path)?;
 
         println!(
             "Success in building Android App Bundle. Bundle is available at: {:?}",
@@ -465,22 +1450,276 @@ impl AndroidBuilder {
         Ok(aab_path)
     }
 
+    fn write_gradle_project(
+        &self,
+        out_dir: &Path,
+        debug_key: Option<&SigningKey>,
+        release_key: Option<&SigningKey>,
+    ) -> Result<(), PistonError> {
+        let signing: Vec<(&str, &SigningKey)> = [("debug", debug_key), ("release", release_key)]
+            .into_iter()
+            .filter_map(|(build_type, key)| key.map(|key| (build_type, key)))
+            .collect();
+        let main_dir = out_dir.join("app").join("src").join("main");
+        let libs_dir = out_dir.join("app").join("libs");
+        for dir in [&main_dir, &libs_dir] {
+            create_dir_all(dir).map_err(|e| PistonError::CreateDirAllError {
+                path: dir.clone(),
+                source: e,
+            })?;
+        }
+        Helper::write_file(
+            &main_dir.join("AndroidManifest.xml"),
+            &self.manifest.to_xml(true),
+        )?;
+        //generated icons, banner, firebase values and network security config
+        Helper::sync_assets(&self.resources, &main_dir.join("res"), &[])?;
+        if !self.asset_packs.is_empty() {
+            println!(
+                "⚠️  asset packs are exported as regular assets of the app module, move them into asset pack modules to keep them out of the base module"
+            );
+        }
+        Helper::sync_assets(Path::new(&self.assets), &main_dir.join("assets"), &[])?;
+
+        //add_lib lays out lib/<abi>, gradle picks native libraries up from jniLibs/<abi>
+        //the libs stay unstripped, gradle strips them itself when packaging
+        self.add_lib(&main_dir, &self.target)?;
+        let abi = Self::android_abi(&self.target)?;
+        let jni_dir = main_dir.join("jniLibs").join(abi);
+        Helper::empty_directory(&jni_dir, &[])?;
+        create_dir_all(&jni_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: jni_dir.clone(),
+            source: e,
+        })?;
+        let lib_dir = main_dir.join("lib");
+        rename(lib_dir.join(abi), &jni_dir).map_err(|e| PistonError::RenameFileError {
+            path: lib_dir.join(abi),
+            source: e,
+        })?;
+        std::fs::remove_dir_all(&lib_dir).map_err(|e| PistonError::RemoveSubdirError {
+            path: lib_dir.clone(),
+            source: e,
+        })?;
+
+        //gradle compiles java_src itself instead of the d8 step of a build
+        let java_dir = main_dir.join("java");
+        let mut kotlin = false;
+        if let Some(java_src) = &self.java_src {
+            Helper::sync_assets(java_src, &java_dir, &[])?;
+            kotlin = !Helper::collect_files(java_src, "kt")?.is_empty();
+        }
+        if self.manifest.photo_picker {
+            let picker_dir = java_dir.join("com").join("maverick").join("photo");
+            create_dir_all(&picker_dir).map_err(|e| PistonError::CreateDirAllError {
+                path: picker_dir.clone(),
+                source: e,
+            })?;
+            let picker_src = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("src/android_activity/PhotoPickerActivity.java");
+            copy(&picker_src, picker_dir.join("PhotoPickerActivity.java")).map_err(|e| {
+                PistonError::CopyFileError {
+                    input_path: picker_src.clone(),
+                    output_path: picker_dir.join("PhotoPickerActivity.java"),
+                    source: e,
+                }
+            })?;
+        }
+        for lib in self.java_libs.iter().chain(&self.game_activity_libs) {
+            let lib_path = self.cwd.join(lib);
+            let file_name =
+                lib_path
+                    .file_name()
+                    .ok_or(PistonError::AndroidConfigError(format!(
+                        "invalid java library path: {}",
+                        lib
+                    )))?;
+            copy(&lib_path, libs_dir.join(file_name)).map_err(|e| PistonError::CopyFileError {
+                input_path: lib_path.clone(),
+                output_path: libs_dir.join(file_name),
+                source: e,
+            })?;
+        }
+
+        Helper::write_file(&out_dir.join("settings.gradle"), &self.gradle_settings())?;
+        Helper::write_file(&out_dir.join("build.gradle"), &Self::gradle_root(kotlin))?;
+        Helper::write_file(
+            &out_dir.join("gradle.properties"),
+            "android.useAndroidX=true\norg.gradle.jvmargs=-Xmx2048m\n",
+        )?;
+        Helper::write_file(
+            &out_dir.join("app").join("build.gradle"),
+            &self.gradle_app(kotlin, &signing),
+        )?;
+        //passwords stay out of build.gradle and version control
+        Helper::write_file(
+            &out_dir.join("keystore.properties"),
+            &Self::gradle_keystore_properties(&signing),
+        )?;
+        Helper::write_file(
+            &out_dir.join(".gitignore"),
+            ".gradle/\nbuild/\napp/build/\nlocal.properties\nkeystore.properties\n",
+        )?;
+        Ok(())
+    }
+
+    fn gradle_settings(&self) -> String {
+        format!(
+            r#"pluginManagement {{
+    repositories {{
+        google()
+        mavenCentral()
+        gradlePluginPortal()
+    }}
+}}
+dependencyResolutionManagement {{
+    repositoriesMode.set(RepositoriesMode.FAIL_ON_PROJECT_REPOS)
+    repositories {{
+        google()
+        mavenCentral()
+    }}
+}}
+rootProject.name = {}
+include ':app'
+"#,
+            Self::groovy_string(&self.app_name)
+        )
+    }
+
+    //single quoted groovy literal, which never interpolates $ unlike double quoted strings
+    fn groovy_string(value: &str) -> String {
+        format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+
+    fn gradle_root(kotlin: bool) -> String {
+        let kotlin_plugin = if kotlin {
+            "\n    id 'org.jetbrains.kotlin.android' version '1.9.24' apply false"
+        } else {
+            ""
+        };
+        format!(
+            "plugins {{\n    id 'com.android.application' version '8.5.2' apply false{}\n}}\n",
+            kotlin_plugin
+        )
+    }
+
+    //<buildType>StoreFile, <buildType>StorePassword and <buildType>KeyAlias for every signed build type
+    fn gradle_keystore_properties(signing: &[(&str, &SigningKey)]) -> String {
+        //backslashes are escapes in .properties files and a line break would end the value
+        let escape = |value: &str| {
+            value
+                .replace('\\', "\\\\")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        };
+        signing
+            .iter()
+            .map(|(build_type, key)| {
+                format!(
+                    "{build_type}StoreFile={}\n{build_type}StorePassword={}\n{build_type}KeyAlias={}\n",
+                    escape(&key.path),
+                    escape(&key.pass),
+                    escape(&key.alias),
+                )
+            })
+            .collect()
+    }
+
+    fn gradle_app(&self, kotlin: bool, signing: &[(&str, &SigningKey)]) -> String {
+        let kotlin_plugin = if kotlin {
+            "\n    id 'org.jetbrains.kotlin.android'"
+        } else {
+            ""
+        };
+        let suffix = match &self.application_id_suffix {
+            Some(suffix) => format!(
+                "\n            applicationIdSuffix {}",
+                Self::groovy_string(suffix)
+            ),
+            None => String::new(),
+        };
+        let signing_configs: String = signing
+            .iter()
+            .map(|(build_type, _)| {
+                format!(
+                    r#"
+        {build_type} {{
+            storeFile file(keystoreProperties['{build_type}StoreFile'])
+            storePassword keystoreProperties['{build_type}StorePassword']
+            keyAlias keystoreProperties['{build_type}KeyAlias']
+            keyPassword keystoreProperties['{build_type}StorePassword']
+        }}"#
+                )
+            })
+            .collect();
+        let signing_config = |build_type: &str| {
+            if signing.iter().any(|(signed, _)| *signed == build_type) {
+                format!("\n            signingConfig signingConfigs.{}", build_type)
+            } else {
+                String::new()
+            }
+        };
+        format!(
+            r#"plugins {{
+    id 'com.android.application'{kotlin_plugin}
+}}
+
+def keystoreProperties = new Properties()
+keystoreProperties.load(rootProject.file('keystore.properties').newDataInputStream())
+
+android {{
+    namespace {package}
+    compileSdk {target_sdk}
+
+    defaultConfig {{
+        applicationId {package}
+        minSdk {min_sdk}
+        targetSdk {target_sdk}
+        versionCode {version_code}
+        versionName {version_name}
+    }}
+
+    signingConfigs {{{signing_configs}
+    }}
+
+    buildTypes {{
+        debug {{{debug_signing}{suffix}
+        }}
+        release {{{release_signing}
+            minifyEnabled false
+        }}
+    }}
+
+    packaging {{
+        jniLibs {{
+            useLegacyPackaging {legacy_packaging}
+        }}
+    }}
+}}
+
+dependencies {{
+    implementation fileTree(dir: 'libs', include: ['*.jar', '*.aar'])
+}}
+"#,
+            kotlin_plugin = kotlin_plugin,
+            package = Self::groovy_string(&self.manifest.package),
+            target_sdk = self.manifest.target_sdk_version,
+            min_sdk = self.manifest.min_sdk_version,
+            version_code = self.manifest.version_code,
+            version_name = Self::groovy_string(&self.manifest.version_name),
+            suffix = suffix,
+            signing_configs = signing_configs,
+            debug_signing = signing_config("debug"),
+            release_signing = signing_config("release"),
+            legacy_packaging = !self.bundle_settings.uncompress_native_libs,
+        )
+    }
+
     fn post_build(&mut self, aab_path: PathBuf) -> Result<(), PistonError> {
         println!("post build for android");
-        //create a release key if none specified in .env and release flag is true
-        let key_path_exists = Path::new(&self.key_path).to_path_buf().exists();
-        let key_alias_exists = self.verify_key_alias()?;
-        if self.release && (!key_path_exists || !key_alias_exists) {
-            //create a release key
-            self.create_release_key()?;
-        } else if self.release {
-            println!("release key found at: {}", self.key_path);
-        }
-        //sign the completed AAB with release key if release flag is true
-        if self.release {
-            //sign the bundle
-            self.sign_aab(aab_path)?;
-        }
+        //create a release or debug key if none exists at the path on record
+        self.keystore.ensure()?;
+        //sign the completed AAB with the release or debug key
+        self.sign_aab(aab_path)?;
         //TODO if a device target is provided, check if the target device is provisioned
         if !self.device_target.is_none() {
             println!("");
@@ -489,19 +1728,24 @@ impl AndroidBuilder {
         Ok(())
     }
 
-    fn build_so(&mut self) -> Result<(), PistonError> {
-        println!("building the .so library");
-        //build the .so with cargo
-        let host_platform = Helper::get_host_platform(self.ndk_path.as_ref())?;
+    //linker, archiver and c/c++ compiler env for cargo and the cc crate, shared by builds and device tests
+    fn ndk_toolchain_env(
+        ndk_path: &str,
+        target: &str,
+        min_sdk_version: u32,
+        align_16k: bool,
+        cargo_dir: &Path,
+    ) -> Result<Vec<(String, String)>, PistonError> {
+        let host_platform = Helper::get_host_platform(ndk_path)?;
         //set linker
-        let api_level = self.manifest.min_sdk_version.to_string();
+        let api_level = min_sdk_version.to_string();
         // For linker name: for aarch64-linux-android, it's target_triple + api_level + "-clang"
-        let linker_name = if self.target == "armv7-linux-androideabi" {
+        let linker_name = if target == "armv7-linux-androideabi" {
             format!("armv7a-linux-androideabi{}-clang", api_level)
         } else {
-            format!("{}{}-clang", self.target, api_level)
+            format!("{}{}-clang", target, api_level)
         };
-        let ndk_path_buf = PathBuf::from(&self.ndk_path);
+        let ndk_path_buf = PathBuf::from(ndk_path);
         let linker_path = ndk_path_buf
             .join("toolchains/llvm/prebuilt")
             .join(&host_platform)
@@ -528,12 +1772,12 @@ impl AndroidBuilder {
 
         // handle cc crate linker for rusqlite
         //format target triple to aarch64_linux_android
-        let target_underscored = self.target.replace('-', "_");
+        let target_underscored = target.replace('-', "_");
 
-        let cxx_name = if self.target == "armv7-linux-androideabi" {
+        let cxx_name = if target == "armv7-linux-androideabi" {
             format!("armv7a-linux-androideabi{}-clang++", api_level)
         } else {
-            format!("{}{}-clang++", self.target, api_level)
+            format!("{}{}-clang++", target, api_level)
         };
 
         let cxx_path = ndk_path_buf
@@ -556,24 +1800,157 @@ impl AndroidBuilder {
         // also used by cc
         let ar_env_key_cc = format!("AR_{}", target_underscored);
 
-        let target_upper = self.target.to_uppercase().replace("-", "_");
+        let target_upper = target.to_uppercase().replace("-", "_");
         let linker_env_key = format!("CARGO_TARGET_{}_LINKER", target_upper);
         let ar_env_key = format!("CARGO_TARGET_{}_AR", target_upper);
+        let mut toolchain_env = Vec::new();
+        if align_16k {
+            let rustflags_env_key = format!("CARGO_TARGET_{}_RUSTFLAGS", target_upper);
+            //encoded rustflags replace every other source, so carry over whatever cargo would have used
+            //0x1f separated flags keep a link-arg path with spaces intact
+            let mut rustflags = Self::configured_rustflags(cargo_dir, target, &rustflags_env_key);
+            rustflags.push("-C".to_string());
+            rustflags.push("link-arg=-Wl,-z,max-page-size=16384".to_string());
+            toolchain_env.push((
+                "CARGO_ENCODED_RUSTFLAGS".to_string(),
+                rustflags.join("\x1f"),
+            ));
+        }
+        toolchain_env.extend([
+            ("NDK_HOME".to_string(), ndk_path.to_string()),
+            (linker_env_key, linker_path.display().to_string()),
+            (ar_env_key, ar_path.display().to_string()),
+            (cc_env_key, linker_path.display().to_string()),
+            (cxx_env_key, cxx_path.display().to_string()),
+            (ar_env_key_cc, ar_path.display().to_string()),
+        ]);
+        Ok(toolchain_env)
+    }
+
+    //rustflags cargo applies to target without our override, in cargo's order: CARGO_ENCODED_RUSTFLAGS,
+    //RUSTFLAGS, the target's rustflags from the env or target.<triple>.rustflags, otherwise
+    //CARGO_BUILD_RUSTFLAGS or build.rustflags
+    fn configured_rustflags(cargo_dir: &Path, target: &str, target_env_key: &str) -> Vec<String> {
+        let split = |flags: String| -> Vec<String> {
+            flags
+                .split_whitespace()
+                .map(|flag| flag.to_string())
+                .collect()
+        };
+        if let Ok(flags) = std::env::var("CARGO_ENCODED_RUSTFLAGS") {
+            return flags
+                .split('\x1f')
+                .filter(|flag| !flag.is_empty())
+                .map(|flag| flag.to_string())
+                .collect();
+        }
+        if let Ok(flags) = std::env::var("RUSTFLAGS") {
+            return split(flags);
+        }
+        if let Ok(flags) = std::env::var(target_env_key) {
+            return split(flags);
+        }
+        let configs = Self::cargo_configs(cargo_dir);
+        let target_flags = Self::config_rustflags(&configs, &["target", target, "rustflags"]);
+        if !target_flags.is_empty() {
+            return target_flags;
+        }
+        if let Ok(flags) = std::env::var("CARGO_BUILD_RUSTFLAGS") {
+            return split(flags);
+        }
+        Self::config_rustflags(&configs, &["build", "rustflags"])
+    }
+
+    //.cargo/config.toml files from cargo_dir up to the root, then $CARGO_HOME, closest first
+    fn cargo_configs(cargo_dir: &Path) -> Vec<toml::Table> {
+        let cargo_home = std::env::var("CARGO_HOME")
+            .map(PathBuf::from)
+            .ok()
+            .or_else(|| {
+                std::env::var("HOME")
+                    .ok()
+                    .map(|home| Path::new(&home).join(".cargo"))
+            });
+        let mut dirs: Vec<PathBuf> = cargo_dir
+            .ancestors()
+            .map(|dir| dir.join(".cargo"))
+            .collect();
+        if let Some(cargo_home) = cargo_home
+            && !dirs.contains(&cargo_home)
+        {
+            dirs.push(cargo_home);
+        }
+        dirs.iter()
+            .filter_map(|dir| {
+                ["config.toml", "config"]
+                    .iter()
+                    .map(|name| dir.join(name))
+                    .find(|path| path.is_file())
+            })
+            .filter_map(|path| {
+                std::fs::read_to_string(path)
+                    .ok()?
+                    .parse::<toml::Table>()
+                    .ok()
+            })
+            .collect()
+    }
+
+    //rustflags at key in every config, given either as an array or a space separated string
+    fn config_rustflags(configs: &[toml::Table], key: &[&str]) -> Vec<String> {
+        let mut flags = Vec::new();
+        for config in configs {
+            let value = key[1..].iter().fold(config.get(key[0]), |value, part| {
+                value.and_then(|value| value.get(*part))
+            });
+            match value {
+                Some(toml::Value::Array(items)) => flags.extend(
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(|flag| flag.to_string())),
+                ),
+                Some(toml::Value::String(text)) => {
+                    flags.extend(text.split_whitespace().map(|flag| flag.to_string()))
+                }
+                _ => {}
+            }
+        }
+        flags
+    }
+
+    fn build_so(&mut self) -> Result<(), PistonError> {
+        println!("building the .so library");
+        //build the .so with cargo
+        let toolchain_env = Self::ndk_toolchain_env(
+            &self.ndk_path,
+            &self.target,
+            self.manifest.min_sdk_version,
+            self.align_16k,
+            &self.build_path,
+        )?;
         let release = if self.release { "--release" } else { "" };
-        let cargo_command = format!("cargo build --target {}  {} --lib", self.target, release);
+        //shim builds share the project's target dir so add_lib finds the .so in the usual place
+        let manifest_args = if self.shim.is_some() {
+            format!(
+                "--manifest-path '{}' --target-dir '{}'",
+                self.shim_dir().join("Cargo.toml").display(),
+                self.cwd.join("target").display()
+            )
+        } else {
+            String::new()
+        };
+        let cargo_command = format!(
+            "cargo build --target {}  {} --lib {}",
+            self.target, release, manifest_args
+        );
         //run the cargo build command
         let builder = Command::new("bash")
             .arg("-c")
             .arg(&cargo_command)
             .current_dir(self.build_path.clone())
+            .envs(toolchain_env)
             .env("JAVA_HOME", self.java_path.clone())
             .env("ANDROID_HOME", self.sdk_path.clone())
-            .env("NDK_HOME", self.ndk_path.clone())
-            .env(&linker_env_key, linker_path.to_str().unwrap())
-            .env(&ar_env_key, ar_path.to_str().unwrap())
-            .env(&cc_env_key, linker_path.to_str().unwrap())
-            .env(&cxx_env_key, cxx_path.to_str().unwrap())
-            .env(&ar_env_key_cc, ar_path.to_str().unwrap())
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .output()
@@ -627,23 +2004,27 @@ impl AndroidBuilder {
     ) -> Result<(), PistonError> {
         let aapt2_path: PathBuf = PathBuf::from(self.sdk_path.clone())
             .join(format!("build-tools/{}/aapt2", self.build_tools_version));
-        let android_jar: PathBuf = PathBuf::from(self.sdk_path.clone()).join(format!(
-            "platforms/android-{}/android.jar",
-            self.manifest.target_sdk_version
-        ));
+        let android_jar: &Path = &self.android_jar;
 
         let res_arg = if compiled_res.exists() {
             format!(" {}", compiled_res.display())
         } else {
             String::new()
         };
+        //rename the application id without touching the manifest namespace used by java sources
+        let rename_arg = if self.application_id != self.manifest.package {
+            format!(" --rename-manifest-package {}", self.application_id)
+        } else {
+            String::new()
+        };
         println!("linking manifest & resources");
         let link_command = format!(
-            "{} link --proto-format --output-to-dir -o {} --manifest {} -I {} {}",
+            "{} link --proto-format --output-to-dir -o {} --manifest {} -I {}{} {}",
             aapt2_path.display(),
             base_dir.display(),
             self.manifest_path.display(),
             android_jar.display(),
+            rename_arg,
             res_arg
         );
 
@@ -682,17 +2063,89 @@ impl AndroidBuilder {
         Ok(())
     }
 
-    fn add_lib(&self, base_dir: &Path, target: &str) -> Result<(), PistonError> {
-        println!("adding .so library to base directory");
-        let abi = match target {
-            "aarch64-linux-android" => "arm64-v8a",
-            //Add more mappings here as required if updating android support for other outputs
-            _ => {
-                return Err(PistonError::UnsupportedTargetError(
-                    format!("Unsupported target {}", target).to_string(),
-                ));
+    //each pack is zipped as target/<profile>/androidbuilder/asset_packs/<name>.zip
+    fn build_asset_packs(&self) -> Result<Vec<PathBuf>, PistonError> {
+        let packs_dir = self.build_path.join("asset_packs");
+        let assets_src = Path::new(&self.assets);
+        let mut zips = Vec::new();
+        for pack in &self.asset_packs {
+            let pack_src = assets_src.join(&pack.path);
+            if !pack_src.is_dir() {
+                return Err(PistonError::ParseManifestError(format!(
+                    "Asset pack {} directory not found: {}",
+                    pack.name,
+                    pack_src.display()
+                )));
             }
-        };
+            println!("building {:?} asset pack {}", pack.delivery, pack.name);
+            let pack_dir = packs_dir.join(&pack.name);
+            //keep the synced assets between builds
+            Helper::empty_directory(&pack_dir, &["assets"])?;
+            create_dir_all(&pack_dir).map_err(|e| PistonError::CreateDirAllError {
+                path: pack_dir.clone(),
+                source: e,
+            })?;
+            Helper::sync_assets(&pack_src, &pack_dir.join("assets").join(&pack.path), &[])?;
+            self.link_asset_pack_manifest(pack, &packs_dir, &pack_dir)?;
+            let zip_path = packs_dir.join(format!("{}.zip", pack.name));
+            self.zip_module(&pack_dir, &zip_path)?;
+            zips.push(zip_path);
+        }
+        Ok(zips)
+    }
+
+    //compile the pack manifest to proto format, bundletool rejects resource tables in asset packs
+    fn link_asset_pack_manifest(
+        &self,
+        pack: &AssetPack,
+        packs_dir: &Path,
+        pack_dir: &Path,
+    ) -> Result<(), PistonError> {
+        let aapt2_path: PathBuf = PathBuf::from(self.sdk_path.clone())
+            .join(format!("build-tools/{}/aapt2", self.build_tools_version));
+        let manifest_path = packs_dir.join(format!("{}_AndroidManifest.xml", pack.name));
+        Helper::write_file(&manifest_path, &pack.manifest_xml(&self.application_id))?;
+        let linked_dir = packs_dir.join(format!("{}_linked", pack.name));
+        Helper::empty_directory(&linked_dir, &[])?;
+        let output = Command::new(&aapt2_path)
+            .arg("link")
+            .arg("--proto-format")
+            .arg("--output-to-dir")
+            .arg("-o")
+            .arg(&linked_dir)
+            .arg("--manifest")
+            .arg(&manifest_path)
+            .arg("-I")
+            .arg(&self.android_jar)
+            .env("ANDROID_HOME", self.sdk_path.clone())
+            .output()
+            .map_err(|e| PistonError::ProtoLinkError(format!("aapt2 link failed: {}", e)))?;
+        let linked_manifest = linked_dir.join("AndroidManifest.xml");
+        if !output.status.success() || !linked_manifest.exists() {
+            return Err(PistonError::ProtoLinkError(format!(
+                "failed to link the {} asset pack manifest: {}",
+                pack.name,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        let manifest_dir = pack_dir.join("manifest");
+        create_dir_all(&manifest_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: manifest_dir.clone(),
+            source: e,
+        })?;
+        rename(&linked_manifest, manifest_dir.join("AndroidManifest.xml")).map_err(|e| {
+            PistonError::RenameFileError {
+                path: linked_manifest.clone(),
+                source: e,
+            }
+        })?;
+        Ok(())
+    }
+
+    //returns the file names of the libraries copied into lib/<abi>
+    fn add_lib(&self, base_dir: &Path, target: &str) -> Result<Vec<String>, PistonError> {
+        println!("adding .so library to base directory");
+        let abi = Self::android_abi(target)?;
         let lib_dir = base_dir.join("lib").join(abi);
         Helper::empty_directory(&lib_dir, &[])?;
         create_dir_all(&lib_dir).map_err(|e| PistonError::CreateDirAllError {
@@ -709,171 +2162,507 @@ impl AndroidBuilder {
             .join(&lib_file);
         copy(&so_path, lib_dir.join(&lib_file))
             .map_err(|e| PistonError::BuildError(format!("Failed to copy .so: {}", e)))?;
+        let mut needed: Vec<String> = Elf::open(&so_path)?
+            .needed_libs()
+            .map_err(PistonError::ElfParseError)?;
+        let mut bundled: Vec<String> = vec![lib_file];
+
+        //copy the prebuilt libraries configured for this abi
+        for extra in self.native_libs.get(abi).into_iter().flatten() {
+            let extra_path = self.cwd.join(extra);
+            let file_name = extra_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or(PistonError::AndroidConfigError(format!(
+                    "invalid native library path: {}",
+                    extra
+                )))?;
+            println!("adding prebuilt library {} for {}", file_name, abi);
+            copy(&extra_path, lib_dir.join(&file_name)).map_err(|e| {
+                PistonError::CopyFileError {
+                    input_path: extra_path.clone(),
+                    output_path: lib_dir.join(&file_name),
+                    source: e,
+                }
+            })?;
+            needed.extend(
+                Elf::open(&extra_path)?
+                    .needed_libs()
+                    .map_err(PistonError::ElfParseError)?,
+            );
+            bundled.push(file_name);
+        }
+
+        //C++ code compiled through the cc crate links against the shared STL from the NDK
+        if needed.iter().any(|lib| lib == LIBCXX_SHARED)
+            && !bundled.iter().any(|lib| lib == LIBCXX_SHARED)
+        {
+            let libcxx_path =
+                Self::ndk_sysroot_lib_dir(&self.ndk_path, target)?.join(LIBCXX_SHARED);
+            println!("adding {} from {}", LIBCXX_SHARED, libcxx_path.display());
+            copy(&libcxx_path, lib_dir.join(LIBCXX_SHARED)).map_err(|e| {
+                PistonError::CopyFileError {
+                    input_path: libcxx_path.clone(),
+                    output_path: lib_dir.join(LIBCXX_SHARED),
+                    source: e,
+                }
+            })?;
+            bundled.push(LIBCXX_SHARED.to_string());
+        }
+
+        self.check_page_alignment(&lib_dir, &bundled)?;
+
+        //anything that is neither bundled nor provided by the platform will fail to load at runtime
+        needed.sort();
+        needed.dedup();
+        for lib in needed {
+            if !bundled.contains(&lib) && !ANDROID_SYSTEM_LIBS.contains(&lib.as_str()) {
+                println!(
+                    "⚠️  {} depends on {} which is not bundled, add it to [package.metadata.android.native_libs] if it is not provided by the device",
+                    abi, lib
+                );
+            }
+        }
+
+        Ok(bundled)
+    }
+
+    //keep an unstripped copy of each library in native_debug_symbols/<abi> and strip the bundled one with llvm-strip
+    fn strip_libs(&self, abi: &str, lib_dir: &Path, libs: &[String]) -> Result<(), PistonError> {
+        let host_platform = Helper::get_host_platform(&self.ndk_path)?;
+        let strip_path = PathBuf::from(&self.ndk_path)
+            .join("toolchains/llvm/prebuilt")
+            .join(host_platform)
+            .join("bin")
+            .join("llvm-strip");
+        if !strip_path.exists() {
+            return Err(PistonError::BuildError(format!(
+                "llvm-strip not found at {}",
+                strip_path.display()
+            )));
+        }
+        let symbols_dir = self.build_path.join(NATIVE_DEBUG_SYMBOLS).join(abi);
+        Helper::empty_directory(&self.build_path.join(NATIVE_DEBUG_SYMBOLS), &[])?;
+        create_dir_all(&symbols_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: symbols_dir.clone(),
+            source: e,
+        })?;
+        for lib in libs {
+            println!("stripping {} for {}", lib, abi);
+            copy(lib_dir.join(lib), symbols_dir.join(lib)).map_err(|e| {
+                PistonError::CopyFileError {
+                    input_path: lib_dir.join(lib),
+                    output_path: symbols_dir.join(lib),
+                    source: e,
+                }
+            })?;
+            let output = Command::new(&strip_path)
+                .arg("--strip-unneeded")
+                .arg(lib_dir.join(lib))
+                .output()
+                .map_err(|e| PistonError::BuildError(format!("llvm-strip failed: {}", e)))?;
+            if !output.status.success() {
+                return Err(PistonError::BuildError(format!(
+                    "llvm-strip failed for {}: {}",
+                    lib,
+                    String::from_utf8_lossy(&output.stderr)
+                )));
+            }
+        }
+        Ok(())
+    }
 
+    //zip native_debug_symbols/<abi>/*.so next to the AAB for Play Console and list them as bundle metadata
+    fn native_debug_symbols(&self) -> Result<Vec<(String, PathBuf)>, PistonError> {
+        let symbols_dir = self.build_path.join(NATIVE_DEBUG_SYMBOLS);
+        let mut metadata = Vec::new();
+        for lib in Helper::collect_files(&symbols_dir, "so")? {
+            let relative = lib.strip_prefix(&symbols_dir).map_err(|e| {
+                PistonError::BuildError(format!("invalid debug symbols path: {}", e))
+            })?;
+            metadata.push((Self::debug_symbols_metadata_path(relative), lib.clone()));
+        }
+        if metadata.is_empty() {
+            return Ok(metadata);
+        }
+        let zip_path = self
+            .output_path
+            .clone()
+            .unwrap()
+            .join("native-debug-symbols.zip");
+        Archive::new().write_dir(&symbols_dir, &zip_path)?;
+        println!("native debug symbols are available at: {:?}", zip_path);
+        Ok(metadata)
+    }
+
+    //BUNDLE-METADATA path bundletool stores the unstripped library under, e.g. com.android.tools.build.debugsymbols/arm64-v8a/libapp.so.dbg
+    fn debug_symbols_metadata_path(relative: &Path) -> String {
+        let components: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        format!(
+            "com.android.tools.build.debugsymbols/{}.dbg",
+            components.join("/")
+        )
+    }
+
+    //verify every bundled 64-bit library has LOAD segments aligned to at least 16 KB
+    fn check_page_alignment(&self, lib_dir: &Path, libs: &[String]) -> Result<(), PistonError> {
+        if self.page_size_check == "off" {
+            return Ok(());
+        }
+        let mut misaligned = Vec::new();
+        for lib in libs {
+            let elf = Elf::open(&lib_dir.join(lib))?;
+            //16 KB pages only exist on 64-bit devices
+            if !elf.is_64() {
+                continue;
+            }
+            let alignments = elf.load_alignments().map_err(PistonError::ElfParseError)?;
+            if let Some(min) = alignments.iter().min().filter(|min| **min < 16384) {
+                misaligned.push(format!("{} (LOAD alignment {})", lib, min));
+            }
+        }
+        if misaligned.is_empty() {
+            println!("✅ native libraries are 16 KB page aligned");
+            return Ok(());
+        }
+        let message = format!(
+            "native libraries are not 16 KB page aligned: {}. Rebuild them with -Wl,-z,max-page-size=16384 or use NDK r27+",
+            misaligned.join(", ")
+        );
+        if self.page_size_check == "error" {
+            return Err(PistonError::BuildError(message));
+        }
+        println!("⚠️  {}", message);
         Ok(())
     }
 
-    // Every app built by piston ships the PhotoPickerActivity dex (see src/android_activity/)
-    // so maverick_os's photo picker can resolve as a real, manifest-declared Activity capable
-    // of receiving onActivityResult.
-    fn add_activity_dex(&self, base_dir: &Path) -> Result<(), PistonError> {
-        println!("adding photo picker activity dex to base directory");
+    //map a rust target triple to its android abi directory name
+    fn android_abi(target: &str) -> Result<&'static str, PistonError> {
+        match target {
+            "aarch64-linux-android" => Ok("arm64-v8a"),
+            "x86_64-linux-android" => Ok("x86_64"),
+            //Add more mappings here as required if updating android support for other outputs
+            _ => Err(PistonError::UnsupportedTargetError(format!(
+                "Unsupported target {}",
+                target
+            ))),
+        }
+    }
+
+    //<ndk>/toolchains/llvm/prebuilt/<host>/sysroot/usr/lib/<triple>, where the NDK ships its runtime libraries
+    fn ndk_sysroot_lib_dir(ndk_path: &str, target: &str) -> Result<PathBuf, PistonError> {
+        let host_platform = Helper::get_host_platform(ndk_path)?;
+        let triple = if target == "armv7-linux-androideabi" {
+            "arm-linux-androideabi"
+        } else {
+            target
+        };
+        Ok(PathBuf::from(ndk_path)
+            .join("toolchains/llvm/prebuilt")
+            .join(host_platform)
+            .join("sysroot/usr/lib")
+            .join(triple))
+    }
+
+    // Assembles base/dex from every configured source of java code. The prebuilt
+    // PhotoPickerActivity dex (see src/android_activity/) is opt-in so maverick_os's photo
+    // picker can resolve as a real, manifest-declared Activity capable of receiving
+    // onActivityResult. GameActivity classes and user java_src/java_libs are merged with d8.
+    fn add_dex(&self, base_dir: &Path) -> Result<(), PistonError> {
         let dex_dir = base_dir.join("dex");
+        Helper::empty_directory(&dex_dir, &[])?;
+        if !self.manifest.has_code {
+            println!("no java code configured, skipping classes.dex");
+            return Ok(());
+        }
+        println!("adding classes.dex to base directory");
         create_dir_all(&dex_dir).map_err(|e| PistonError::CreateDirAllError {
             path: dex_dir.clone(),
             source: e,
         })?;
 
-        let dex_path =
-            Path::new(env!("CARGO_MANIFEST_DIR")).join("src/android_activity/classes.dex");
-        copy(&dex_path, dex_dir.join("classes.dex")).map_err(|e| {
-            PistonError::BuildError(format!("Failed to copy activity classes.dex: {}", e))
-        })?;
+        let mut inputs: Vec<PathBuf> = Vec::new();
+        if self.manifest.photo_picker {
+            inputs.push(
+                Path::new(env!("CARGO_MANIFEST_DIR")).join("src/android_activity/classes.dex"),
+            );
+        }
+        if self.manifest.activity == GAME_ACTIVITY {
+            inputs.extend(self.game_activity_jars()?);
+        }
+        inputs.extend(self.compile_java()?);
+        for lib in &self.java_libs {
+            let lib_path = self.cwd.join(lib);
+            if !lib_path.exists() {
+                return Err(PistonError::AndroidConfigError(format!(
+                    "java library not found at: {}",
+                    lib_path.display()
+                )));
+            }
+            inputs.push(lib_path);
+        }
 
-        Ok(())
+        if inputs.is_empty() {
+            return Err(PistonError::BuildError(format!(
+                "activity {} requires java classes but none were configured",
+                self.manifest.activity
+            )));
+        }
+        //a lone prebuilt dex needs no merging
+        if inputs.len() == 1 && inputs[0].extension().and_then(|ext| ext.to_str()) == Some("dex") {
+            copy(&inputs[0], dex_dir.join("classes.dex")).map_err(|e| {
+                PistonError::BuildError(format!("Failed to copy classes.dex: {}", e))
+            })?;
+            return Ok(());
+        }
+        self.run_d8(&inputs, &dex_dir)
     }
 
-    fn zip_base(&self, base_dir: &Path) -> Result<(), PistonError> {
-        let zip_path = self.build_path.join("base.zip");
-        if zip_path.exists() {
-            remove_file(&zip_path).map_err(|e| PistonError::RemoveFileError {
-                path: zip_path.clone().to_path_buf(),
-                source: e,
+    //compile java_src with javac (and kotlinc for .kt files) against android.jar, returning the class files
+    fn compile_java(&self) -> Result<Vec<PathBuf>, PistonError> {
+        let src_dir = match &self.java_src {
+            Some(dir) => dir,
+            None => return Ok(Vec::new()),
+        };
+        if !src_dir.is_dir() {
+            return Err(PistonError::AndroidConfigError(format!(
+                "java_src directory not found at: {}",
+                src_dir.display()
+            )));
+        }
+        println!("compiling java sources at {:?}", src_dir);
+        let classes_dir = self.build_path.join("classes");
+        Helper::empty_directory(&classes_dir, &[])?;
+        create_dir_all(&classes_dir).map_err(|e| PistonError::CreateDirAllError {
+            path: classes_dir.clone(),
+            source: e,
+        })?;
+        let android_jar: &Path = &self.android_jar;
+        let java_sources = Helper::collect_files(src_dir, "java")?;
+        let kotlin_sources = Helper::collect_files(src_dir, "kt")?;
+        let mut classpath: Vec<String> = self
+            .java_libs
+            .iter()
+            .map(|lib| self.cwd.join(lib).display().to_string())
+            .collect();
+        let mut extra_inputs: Vec<PathBuf> = Vec::new();
+
+        if !kotlin_sources.is_empty() {
+            let kotlinc = self.kotlinc_path.as_ref().ok_or_else(|| {
+                PistonError::AndroidConfigError(
+                    "kotlin sources found in java_src but kotlinc_path is not set in .env"
+                        .to_string(),
+                )
             })?;
+            let mut kotlin_classpath = classpath.clone();
+            kotlin_classpath.push(android_jar.display().to_string());
+            let output = Command::new(kotlinc)
+                .arg("-no-reflect")
+                .arg("-jvm-target")
+                .arg("1.8")
+                .arg("-classpath")
+                .arg(kotlin_classpath.join(":"))
+                .arg("-d")
+                .arg(&classes_dir)
+                .args(&kotlin_sources)
+                .args(&java_sources)
+                .env("JAVA_HOME", self.java_path.clone())
+                .output()
+                .map_err(|e| PistonError::BuildError(format!("kotlinc failed: {}", e)))?;
+            if !output.status.success() {
+                return Err(PistonError::BuildError(format!(
+                    "kotlinc failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                )));
+            }
+            //kotlin code depends on the stdlib shipped alongside kotlinc
+            let stdlib = Path::new(kotlinc)
+                .parent()
+                .and_then(|bin| bin.parent())
+                .map(|home| home.join("lib").join("kotlin-stdlib.jar"))
+                .filter(|jar| jar.exists())
+                .ok_or_else(|| {
+                    PistonError::AndroidConfigError(format!(
+                        "kotlin-stdlib.jar not found next to kotlinc at: {}",
+                        kotlinc
+                    ))
+                })?;
+            classpath.push(classes_dir.display().to_string());
+            extra_inputs.push(stdlib);
         }
-        println!("zipping base dir");
-        let zip_command = format!("cd {} && zip -r ../base.zip *", base_dir.display());
 
-        Command::new("bash")
-            .arg("-c")
-            .arg(&zip_command)
-            .current_dir(&self.build_path)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()
-            .map_err(|e| PistonError::BuildError(format!("Zip failed: {}", e)))?;
+        if !java_sources.is_empty() {
+            let javac_path = PathBuf::from(&self.java_path).join("bin").join("javac");
+            let mut javac = Command::new(&javac_path);
+            javac
+                .arg("-source")
+                .arg("8")
+                .arg("-target")
+                .arg("8")
+                .arg("-Xlint:-options")
+                .arg("-bootclasspath")
+                .arg(android_jar)
+                .arg("-d")
+                .arg(&classes_dir);
+            if !classpath.is_empty() {
+                javac.arg("-classpath").arg(classpath.join(":"));
+            }
+            let output = javac
+                .args(&java_sources)
+                .env("JAVA_HOME", self.java_path.clone())
+                .output()
+                .map_err(|e| PistonError::BuildError(format!("javac failed: {}", e)))?;
+            if !output.status.success() {
+                return Err(PistonError::BuildError(format!(
+                    "javac failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                )));
+            }
+        }
 
-        Ok(())
+        let mut classes = Helper::collect_files(&classes_dir, "class")?;
+        classes.extend(extra_inputs);
+        Ok(classes)
     }
 
-    fn build_bundle(&self, base_zip: &Path, aab_path: &Path) -> Result<(), PistonError> {
-        println!("building .aab bundle with bundletool");
-        if aab_path.exists() {
-            remove_file(&aab_path).map_err(|e| PistonError::RemoveFileError {
-                path: aab_path.to_path_buf(),
+    //resolve the configured GameActivity libraries to class jars, extracting classes.jar from any .aar
+    fn game_activity_jars(&self) -> Result<Vec<PathBuf>, PistonError> {
+        let extract_dir = self.build_path.join("game_activity");
+        Helper::empty_directory(&extract_dir, &[])?;
+        let mut jars = Vec::new();
+        for lib in &self.game_activity_libs {
+            let lib_path = self.cwd.join(lib);
+            if !lib_path.exists() {
+                return Err(PistonError::AndroidConfigError(format!(
+                    "game activity library not found at: {}",
+                    lib_path.display()
+                )));
+            }
+            if lib_path.extension().and_then(|ext| ext.to_str()) != Some("aar") {
+                jars.push(lib_path);
+                continue;
+            }
+            let stem = lib_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let out_dir = extract_dir.join(&stem);
+            create_dir_all(&out_dir).map_err(|e| PistonError::CreateDirAllError {
+                path: out_dir.clone(),
                 source: e,
             })?;
+            Archive::extract_file(&lib_path, "classes.jar", &out_dir.join("classes.jar"))?;
+            jars.push(out_dir.join("classes.jar"));
         }
-        let bundle_command = format!(
-            "java -jar {} build-bundle --modules={} --output={}",
-            self.bundletool_path,
-            base_zip.display(),
-            aab_path.display()
-        );
+        Ok(jars)
+    }
 
-        Command::new("bash")
-            .arg("-c")
-            .arg(&bundle_command)
-            .current_dir(&self.build_path)
+    //compile class jars and dex files into the dex output dir with d8
+    fn run_d8(&self, inputs: &[PathBuf], dex_dir: &Path) -> Result<(), PistonError> {
+        println!("merging dex inputs with d8: {:?}", inputs);
+        let sdk = PathBuf::from(&self.sdk_path);
+        let d8_path = sdk.join(format!("build-tools/{}/d8", self.build_tools_version));
+        let android_jar: &Path = &self.android_jar;
+        let output = Command::new(&d8_path)
+            .arg(if self.release { "--release" } else { "--debug" })
+            .arg("--min-api")
+            .arg(self.manifest.min_sdk_version.to_string())
+            .arg("--lib")
+            .arg(android_jar)
+            .arg("--output")
+            .arg(dex_dir)
+            .args(inputs)
             .env("JAVA_HOME", self.java_path.clone())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
             .output()
-            .map_err(|e| PistonError::BuildError(format!("bundletool failed: {}", e)))?;
+            .map_err(|e| PistonError::BuildError(format!("d8 failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(PistonError::BuildError(format!(
+                "d8 failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
         Ok(())
     }
 
-    fn create_release_key(&self) -> Result<(), PistonError> {
-        //proceed to key creation, state the reason for the user
-        println!(
-            "creating release key at path: {} with the alias: {}",
-            self.key_path, self.key_alias
-        );
-        //check if .android exists, if not create
-        if let Some(parent) = Path::new(&self.key_path).parent() {
-            create_dir_all(parent).map_err(|e| PistonError::CreateDirAllError {
-                path: Path::new(&self.key_path).to_path_buf(),
+    fn zip_module(&self, module_dir: &Path, zip_path: &Path) -> Result<(), PistonError> {
+        if zip_path.exists() {
+            remove_file(zip_path).map_err(|e| PistonError::RemoveFileError {
+                path: zip_path.to_path_buf(),
                 source: e,
             })?;
         }
+        //resources.arsc must stay uncompressed for targetSdk 30+, native libs follow the bundle setting
+        let mut archive = Archive::new().store_name("resources.arsc");
+        if self.bundle_settings.uncompress_native_libs {
+            archive = archive.store_extension("so");
+        }
+        archive.write_dir(module_dir, zip_path)
+    }
 
-        //create release key with keytool
-        let output = Command::new("keytool")
-            .arg("-genkeypair")
-            .arg("-v")
-            .arg("-keystore")
-            .arg(self.key_path.clone())
-            .arg("-storepass")
-            .arg(self.key_pass.clone())
-            .arg("-keypass")
-            .arg(self.key_pass.clone())
-            .arg("-alias")
-            .arg(self.key_alias.clone())
-            .arg("-keyalg")
-            .arg("RSA")
-            .arg("-keysize")
-            .arg("2048")
-            .arg("-validity")
-            .arg("10000")
-            .arg("-dname")
+    fn build_bundle(
+        &self,
+        modules: &[PathBuf],
+        texture_formats: &[String],
+        metadata_files: &[(String, PathBuf)],
+        aab_path: &Path,
+    ) -> Result<(), PistonError> {
+        println!("building .aab bundle with bundletool");
+        if aab_path.exists() {
+            remove_file(&aab_path).map_err(|e| PistonError::RemoveFileError {
+                path: aab_path.to_path_buf(),
+                source: e,
+            })?;
+        }
+        //generate BundleConfig.json from [package.metadata.android.bundle]
+        let config_path = self.build_path.join("BundleConfig.json");
+        let config =
+            serde_json::to_string_pretty(&self.bundle_settings.to_config(texture_formats)?)
+                .map_err(|e| PistonError::IntoJSONError(e.to_string()))?;
+        Helper::write_file(&config_path, &config)?;
+        let mut command = Command::new("java");
+        command
+            .arg("-jar")
+            .arg(&self.bundletool_path)
+            .arg("build-bundle")
             .arg(format!(
-                "CN={}, OU={}, O={}, L={}, S={}, C={}",
-                self.common_name, self.org_unit, self.org, self.locality, self.state, self.country
+                "--modules={}",
+                modules
+                    .iter()
+                    .map(|module| module.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
             ))
-            .output()
-            .map_err(|e| {
-                PistonError::KeyToolError(format!(
-                    "Failed to generate release key with keytool: {}",
-                    e
-                ))
-            })?;
+            .arg(format!("--config={}", config_path.display()))
+            .arg(format!("--output={}", aab_path.display()));
+        //BUNDLE-METADATA entries, e.g. the native debug symbols of release builds
+        for (bundle_path, file) in metadata_files {
+            command.arg(format!(
+                "--metadata-file={}:{}",
+                bundle_path,
+                file.display()
+            ));
+        }
 
+        let output = command
+            .current_dir(&self.build_path)
+            .env("JAVA_HOME", self.java_path.clone())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| PistonError::BuildError(format!("bundletool failed: {}", e)))?;
+        //a rejected BundleConfig.json would otherwise only surface as a missing aab when signing
         if !output.status.success() {
-            return Err(PistonError::KeyToolError(format!(
-                "Failed to generate release key: {}",
+            return Err(PistonError::BuildError(format!(
+                "bundletool build-bundle failed: {}",
                 String::from_utf8_lossy(&output.stderr)
             )));
         }
-
-        println!("Release key successfully created at: {}", self.key_path);
         Ok(())
     }
 
-    fn verify_key_alias(&self) -> Result<bool, PistonError> {
-        //verify the key alias on record exists by querying the keystore
-        let output = Command::new("keytool")
-            .arg("-list")
-            .arg("-v")
-            .arg("-keystore")
-            .arg(self.key_path.clone())
-            .arg("-storepass")
-            .arg(self.key_pass.clone())
-            .output()
-            .map_err(|e| {
-                PistonError::KeyToolError(format!("Failed to list keystore contents: {}", e))
-            })?;
-
-        if !output.status.success() {
-            // let stderr = String::from_utf8_lossy(&output.stderr);
-            return Ok(false);
-            // return Err(PistonError::KeyToolError(format!("Could not use keytool to list keystore contents: {}", stderr)))
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        //search for the alias on record in the keystore
-        for line in stdout.lines() {
-            if let Some(found) = line.strip_prefix("Alias name:") {
-                if found.trim() == self.key_alias {
-                    return Ok(true);
-                } else {
-                    return Ok(false);
-                }
-            }
-        }
-        Ok(false)
-    }
-
     fn sign_aab(&self, aab_path: PathBuf) -> Result<(), PistonError> {
         //sign the AAB with key_path, key_pass, and key_alias on record
         let sdk = PathBuf::from(&self.sdk_path);
@@ -886,13 +2675,13 @@ impl AndroidBuilder {
         let output = Command::new(&apksigner_path)
             .arg("sign")
             .arg("--ks")
-            .arg(self.key_path.clone())
+            .arg(&self.keystore.key.path)
             .arg("--ks-key-alias")
-            .arg(self.key_alias.clone())
+            .arg(&self.keystore.key.alias)
             .arg("--ks-pass")
-            .arg(format!("pass:{}", self.key_pass.clone()))
+            .arg(format!("pass:{}", self.keystore.key.pass))
             .arg("--key-pass")
-            .arg(format!("pass:{}", self.key_pass.clone()))
+            .arg(format!("pass:{}", self.keystore.key.pass))
             .arg("--min-sdk-version")
             .arg(&api_level)
             .arg(&aab_path)
@@ -919,8 +2708,9 @@ impl AndroidBuilder {
         // println!("Signature verifcation: {:?}", output);
 
         println!(
-            "AAB: {} successfully signed for release",
-            aab_path.display()
+            "AAB: {} successfully signed for {}",
+            aab_path.display(),
+            self.keystore.kind()
         );
         Ok(())
     }
@@ -931,17 +2721,19 @@ pub struct AndroidRunner {}
 impl AndroidRunner {
     pub fn start(
         release: bool,
+        follow: bool,
         cwd: PathBuf,
         env_vars: HashMap<String, String>,
         device: &AndroidDevice,
     ) -> Result<(), PistonError> {
         println!("Running for Android");
-        let target_string = "aarch64-linux-android".to_string();
+        //build for the device's primary abi
+        let target_string = device.target()?;
         let env_vars_bind = env_vars.clone();
         //build the app bundle
         let builder = AndroidBuilder::start(
             release,
-            target_string,
+            target_string.clone(),
             cwd.clone(),
             env_vars,
             Some(device.clone()),
@@ -952,20 +2744,77 @@ impl AndroidRunner {
             device.id.as_ref(),
             builder.0,
             builder.1,
-            cwd.clone(),
-            builder.2,
-            env_vars_bind,
+            builder.2.clone(),
+            builder.3,
+            &builder.4,
+            env_vars_bind.clone(),
         )?;
 
+        //stream the app's logs until interrupted
+        if follow {
+            let symbolizer =
+                match AndroidRunner::symbolizer(&cwd, &env_vars_bind, &target_string, release) {
+                    Ok(symbolizer) => Some(symbolizer),
+                    Err(e) => {
+                        println!("WARNING: native crashes will not be symbolicated: {}", e);
+                        None
+                    }
+                };
+            Logcat::start(
+                &env_vars_bind,
+                &device.id,
+                &builder.2,
+                "verbose",
+                symbolizer,
+            )?;
+        }
+
         Ok(())
     }
 
+    //application id of the project in cwd, as installed by a release or debug build
+    pub fn application_id(cwd: &Path, release: bool) -> Result<String, PistonError> {
+        let metadata: Metadata = MetadataCommand::new()
+            .current_dir(cwd)
+            .exec()
+            .map_err(|e| PistonError::CargoParseError(e.to_string()))?;
+        let app_name = Helper::get_app_name(&metadata)?;
+        let app_version = Helper::get_app_version(&metadata)?;
+        let manifest = AndroidManifest::build(&metadata, &app_name, &app_version)?;
+        let android_meta = AndroidMetadata::parse(&metadata)?;
+        Ok(android_meta.application_id(&manifest.package, release))
+    }
+
+    //symbolizer for crashes in the libraries built for target, using the ndk the build would use
+    pub fn symbolizer(
+        cwd: &Path,
+        env_vars: &HashMap<String, String>,
+        target: &str,
+        release: bool,
+    ) -> Result<Symbolizer, PistonError> {
+        let metadata: Metadata = MetadataCommand::new()
+            .current_dir(cwd)
+            .exec()
+            .map_err(|e| PistonError::CargoParseError(e.to_string()))?;
+        let android_meta = AndroidMetadata::parse(&metadata)?;
+        let sdk_path: String = Helper::get_sdk_path(env_vars)?;
+        let ndk_path: String =
+            Helper::get_ndk_path(env_vars, &sdk_path, android_meta.ndk_version.as_deref())?;
+        //cargo keeps the unstripped .so in target/<triple>/<profile>, shim builds included
+        let libs_dir =
+            cwd.join("target")
+                .join(target)
+                .join(if release { "release" } else { "debug" });
+        Symbolizer::new(&ndk_path, libs_dir)
+    }
+
     fn deploy_usb(
         device_id: &str,
         output_path: PathBuf,
         app_name: String,
-        cwd: PathBuf,
         package: String,
+        activity: String,
+        key: &SigningKey,
         env_vars: HashMap<String, String>,
     ) -> Result<(), PistonError> {
         println!(
@@ -976,23 +2825,34 @@ impl AndroidRunner {
         let aab_path = output_path.join(format!("{}.aab", app_name));
         let bundletool_path: &String = Helper::get_or_err(&env_vars, "bundletool_path")?;
         let java_path: &String = Helper::get_or_err(&env_vars, "java_path")?;
-        let sdk_path: &String = Helper::get_or_err(&env_vars, "sdk_path")?;
+        let sdk_path: String = Helper::get_sdk_path(&env_vars)?;
         let adb_path: String = format!("{}/platform-tools/adb", sdk_path);
-        let apk_path = cwd.join(format!("{}.apks", app_name));
+        //keep the extracted apks next to the bundle they were built from
+        let apk_path = output_path.join(format!("{}.apks", app_name));
         //extract .apk from completed aab provided by androidbuilder
-        let bundle_cmd = format!(
-            "java -jar {} build-apks --bundle={} --output={} --connected-device --overwrite --adb {}",
+        println!(
+            "bundletool command: java -jar {} build-apks --bundle={} --output={} --connected-device --overwrite --adb {}",
             &bundletool_path,
             &aab_path.display(),
             &apk_path.display(),
             &adb_path
         );
-        println!("bundletool command: {}", bundle_cmd);
-
-        let output = Command::new("bash")
-            .arg("-c")
-            .arg(&bundle_cmd)
-            .env("JAVA_HOME", &java_path)
+        //sign the extracted apks with the same key as the bundle, kept out of the log above
+        let output = Command::new("java")
+            .arg("-jar")
+            .arg(bundletool_path)
+            .arg("build-apks")
+            .arg(format!("--bundle={}", aab_path.display()))
+            .arg(format!("--output={}", apk_path.display()))
+            .arg("--connected-device")
+            .arg("--overwrite")
+            .arg("--adb")
+            .arg(&adb_path)
+            .arg(format!("--ks={}", key.path))
+            .arg(format!("--ks-key-alias={}", key.alias))
+            .arg(format!("--ks-pass=pass:{}", key.pass))
+            .arg(format!("--key-pass=pass:{}", key.pass))
+            .env("JAVA_HOME", java_path)
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .output()
@@ -1031,7 +2891,7 @@ impl AndroidRunner {
             )));
         }
         //run the app
-        let launch = format!("{}/android.app.NativeActivity", package);
+        let launch = format!("{}/{}", package, activity);
         let adb_cmd = format!("{} shell am start -n {}", &adb_path, &launch,);
 
         let output = Command::new("bash")
@@ -1051,3 +2911,462 @@ impl AndroidRunner {
         Ok(())
     }
 }
+
+//device directory test binaries, their shared libraries and assets are pushed to
+const DEVICE_TEST_DIR: &str = "/data/local/tmp/piston-test";
+
+pub struct AndroidTestRunner {}
+
+impl AndroidTestRunner {
+    //cross compiles the crate's test executables, runs each on the device and returns the combined exit code
+    pub fn start(
+        release: bool,
+        target: String,
+        cwd: PathBuf,
+        env_vars: HashMap<String, String>,
+        device: &AndroidDevice,
+        test_args: Vec<String>,
+    ) -> Result<i32, PistonError> {
+        println!("testing on android device: {}", device.id);
+        let metadata: Metadata = MetadataCommand::new()
+            .current_dir(cwd.clone())
+            .exec()
+            .map_err(|e| PistonError::CargoParseError(e.to_string()))?;
+        let app_name = Helper::get_app_name(&metadata)?;
+        let app_version = Helper::get_app_version(&metadata)?;
+        let manifest = AndroidManifest::build(&metadata, &app_name, &app_version)?;
+        let android_meta = AndroidMetadata::parse(&metadata)?;
+        let sdk_path: String = Helper::get_sdk_path(&env_vars)?;
+        let ndk_path: String =
+            Helper::get_ndk_path(&env_vars, &sdk_path, android_meta.ndk_version.as_deref())?;
+        let adb_path: String = format!("{}/platform-tools/adb", sdk_path);
+
+        let executables = Self::build_tests(
+            release,
+            &target,
+            &cwd,
+            &ndk_path,
+            manifest.min_sdk_version,
+            android_meta.align_16k,
+        )?;
+        if executables.is_empty() {
+            println!("no test executables were built");
+            return Ok(0);
+        }
+
+        //start from a clean directory so stale binaries or assets never leak into a run
+        let remote_dir = format!("{}/{}", DEVICE_TEST_DIR, app_name);
+        Self::adb(
+            &adb_path,
+            &device.id,
+            &[
+                "shell",
+                &format!("rm -rf '{}' && mkdir -p '{}'", remote_dir, remote_dir),
+            ],
+        )?;
+        //native libraries the tests may link against dynamically
+        let libcxx_path =
+            AndroidBuilder::ndk_sysroot_lib_dir(&ndk_path, &target)?.join(LIBCXX_SHARED);
+        let mut libs: Vec<PathBuf> = vec![libcxx_path];
+        if let Ok(abi) = AndroidBuilder::android_abi(&target) {
+            for lib in android_meta.native_libs.get(abi).into_iter().flatten() {
+                libs.push(cwd.join(lib));
+            }
+        }
+        for lib in libs.iter().filter(|lib| lib.exists()) {
+            Self::push(&adb_path, &device.id, lib, &remote_dir)?;
+        }
+        //tests read assets relative to their working directory, as they would from the project root
+        let assets = Helper::get_assets_path(&metadata);
+        if !assets.is_empty() && cwd.join(&assets).exists() {
+            Self::push(&adb_path, &device.id, &cwd.join(&assets), &remote_dir)?;
+        }
+
+        let mut failed: Vec<String> = Vec::new();
+        let mut exit_code = 0;
+        for executable in &executables {
+            let file_name = executable
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            Self::push(&adb_path, &device.id, executable, &remote_dir)?;
+            println!("running {} on {}", file_name, device.id);
+            let args: Vec<String> = test_args
+                .iter()
+                .map(|arg| format!("'{}'", arg.replace('\'', "'\\''")))
+                .collect();
+            let run_command = format!(
+                "cd '{dir}' && chmod 755 '{bin}' && LD_LIBRARY_PATH='{dir}' ./'{bin}' {args}",
+                dir = remote_dir,
+                bin = file_name,
+                args = args.join(" ")
+            );
+            //adb shell forwards the remote exit status since android 7
+            let status = Command::new(&adb_path)
+                .arg("-s")
+                .arg(&device.id)
+                .arg("shell")
+                .arg(&run_command)
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit())
+                .status()
+                .map_err(|e| PistonError::RunAPKError(format!("Failed to run test: {}", e)))?;
+            if !status.success() {
+                failed.push(file_name);
+                exit_code = status.code().unwrap_or(101);
+            }
+        }
+
+        if failed.is_empty() {
+            println!("all {} test executables passed", executables.len());
+        } else {
+            println!(
+                "{} of {} test executables failed: {}",
+                failed.len(),
+                executables.len(),
+                failed.join(", ")
+            );
+        }
+        Ok(exit_code)
+    }
+
+    //cargo test --no-run, returning the test executables cargo reports
+    fn build_tests(
+        release: bool,
+        target: &str,
+        cwd: &Path,
+        ndk_path: &str,
+        min_sdk_version: u32,
+        align_16k: bool,
+    ) -> Result<Vec<PathBuf>, PistonError> {
+        let toolchain_env =
+            AndroidBuilder::ndk_toolchain_env(ndk_path, target, min_sdk_version, align_16k, cwd)?;
+        let mut command = Command::new("cargo");
+        command
+            .arg("test")
+            .arg("--no-run")
+            .arg("--target")
+            .arg(target)
+            .arg("--message-format=json-render-diagnostics");
+        if release {
+            command.arg("--release");
+        }
+        let mut child = command
+            .current_dir(cwd)
+            .envs(toolchain_env)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| PistonError::BuildError(format!("cargo test failed: {}", e)))?;
+        let stdout = child.stdout.take().ok_or(PistonError::BuildError(
+            "Failed to capture cargo test output".to_string(),
+        ))?;
+        let mut executables: Vec<PathBuf> = Vec::new();
+        for message in Message::parse_stream(BufReader::new(stdout)).map_while(Result::ok) {
+            if let Message::CompilerArtifact(artifact) = message
+                && let (true, Some(executable)) = (artifact.profile.test, artifact.executable)
+            {
+                executables.push(executable.into_std_path_buf());
+            }
+        }
+        let status = child
+            .wait()
+            .map_err(|e| PistonError::BuildError(format!("cargo test failed: {}", e)))?;
+        if !status.success() {
+            return Err(PistonError::BuildError(
+                "cargo test failed to compile the test executables".to_string(),
+            ));
+        }
+        Ok(executables)
+    }
+
+    fn push(
+        adb_path: &str,
+        device_id: &str,
+        path: &Path,
+        remote_dir: &str,
+    ) -> Result<(), PistonError> {
+        Self::adb(
+            adb_path,
+            device_id,
+            &["push", &path.display().to_string(), remote_dir],
+        )
+    }
+
+    fn adb(adb_path: &str, device_id: &str, args: &[&str]) -> Result<(), PistonError> {
+        let output = Command::new(adb_path)
+            .arg("-s")
+            .arg(device_id)
+            .args(args)
+            .output()
+            .map_err(|e| PistonError::InstallAPKError(format!("Failed to run adb: {}", e)))?;
+        if !output.status.success() {
+            return Err(PistonError::InstallAPKError(format!(
+                "adb {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_bundle_config_defaults() {
+    let config = BundleSettings::default().to_config(&[]).unwrap();
+    let splits = &config["optimizations"]["splitsConfig"]["splitDimension"];
+    assert_eq!(splits[0], json!({"value": "ABI", "negate": false}));
+    assert_eq!(
+        splits[1],
+        json!({"value": "SCREEN_DENSITY", "negate": true})
+    );
+    assert_eq!(
+        config["optimizations"]["uncompressNativeLibraries"]["enabled"],
+        json!(true)
+    );
+    assert!(config["optimizations"].get("uncompressDexFiles").is_none());
+}
+
+#[test]
+fn test_google_services_values() {
+    let json = json!({
+        "project_info": {
+            "project_number": "123456789012",
+            "firebase_url": "https://demo.firebaseio.com",
+            "project_id": "demo",
+            "storage_bucket": "demo.appspot.com"
+        },
+        "client": [
+            {
+                "client_info": {
+                    "mobilesdk_app_id": "1:123456789012:android:aaaa",
+                    "android_client_info": { "package_name": "com.example.other" }
+                },
+                "api_key": [{ "current_key": "other-key" }]
+            },
+            {
+                "client_info": {
+                    "mobilesdk_app_id": "1:123456789012:android:bbbb",
+                    "android_client_info": { "package_name": "com.example.app" }
+                },
+                "oauth_client": [
+                    { "client_id": "android.apps.googleusercontent.com", "client_type": 1 },
+                    { "client_id": "web.apps.googleusercontent.com", "client_type": 3 }
+                ],
+                "api_key": [{ "current_key": "app-key" }]
+            }
+        ]
+    });
+    let xml = AndroidBuilder::google_services_values(&json, "com.example.app").unwrap();
+    assert!(xml.contains(
+        r#"<string name="google_app_id" translatable="false">1:123456789012:android:bbbb</string>"#
+    ));
+    assert!(xml.contains(
+        r#"<string name="gcm_defaultSenderId" translatable="false">123456789012</string>"#
+    ));
+    assert!(xml.contains(r#"<string name="google_api_key" translatable="false">app-key</string>"#));
+    assert!(xml.contains(r#"<string name="default_web_client_id" translatable="false">web.apps.googleusercontent.com</string>"#));
+    assert!(AndroidBuilder::google_services_values(&json, "com.example.missing").is_err());
+}
+
+#[test]
+fn test_network_security_config() {
+    let config: NetworkSecurity = serde_json::from_value(json!({
+        "cleartext_domains": ["legacy.example.com"],
+        "debug_cleartext_domains": ["192.168.1.20"],
+        "pins": [{
+            "domain": "api.example.com",
+            "include_subdomains": true,
+            "sha256": ["7HIpactkIAq2Y49orFOOQKurWxmmSFZhBCoQYcRhJ3Y=", "fwza0LRMXouZHRC8Ei+4PyuldPDcf3UKgO/04cDM1oE="],
+            "expiration": "2027-01-01"
+        }],
+        "debug_overrides": {}
+    }))
+    .unwrap();
+    let debug = config.to_xml(false).unwrap();
+    assert!(debug.contains(r#"<domain includeSubdomains="false">192.168.1.20</domain>"#));
+    assert!(debug.contains(r#"<certificates src="user" />"#));
+    let release = config.to_xml(true).unwrap();
+    assert!(release.contains(r#"<domain includeSubdomains="false">legacy.example.com</domain>"#));
+    assert!(release.contains(r#"<domain includeSubdomains="true">api.example.com</domain>"#));
+    assert!(release.contains(r#"<pin-set expiration="2027-01-01">"#));
+    assert!(!release.contains("192.168.1.20"));
+    assert!(!release.contains("debug-overrides"));
+
+    let manifest = AndroidManifest {
+        network_security_config: true,
+        ..Default::default()
+    };
+    assert!(
+        manifest
+            .to_xml(false)
+            .contains(r#"android:networkSecurityConfig="@xml/network_security_config""#)
+    );
+}
+
+#[test]
+fn test_form_factor_manifest() {
+    let manifest = AndroidManifest {
+        form_factors: vec![FormFactor::Phone, FormFactor::Tv, FormFactor::Chromeos],
+        ..Default::default()
+    };
+    let xml = manifest.to_xml(false);
+    assert!(xml.contains(
+        r#"<uses-feature android:name="android.software.leanback" android:required="false" />"#
+    ));
+    assert_eq!(xml.matches("android.hardware.touchscreen").count(), 1);
+    assert!(xml.contains(r#"android:banner="@drawable/banner""#));
+    assert!(xml.contains("android.intent.category.LEANBACK_LAUNCHER"));
+    assert!(xml.contains(r#"android:resizeableActivity="true""#));
+    assert!(!xml.contains("android.hardware.type.watch"));
+
+    let wear = AndroidManifest {
+        form_factors: vec![FormFactor::Wear],
+        ..Default::default()
+    }
+    .to_xml(false);
+    assert!(wear.contains(r#"<uses-feature android:name="android.hardware.type.watch" />"#));
+    assert!(wear.contains("com.google.android.wearable.standalone"));
+    assert!(!wear.contains("LEANBACK_LAUNCHER"));
+}
+
+#[test]
+fn test_asset_pack_manifest() {
+    let pack: AssetPack = serde_json::from_value(json!({
+        "name": "level_textures",
+        "path": "levels",
+        "delivery": "fast-follow"
+    }))
+    .unwrap();
+    assert!(pack.validate().is_ok());
+    let xml = pack.manifest_xml("com.example.app");
+    assert!(xml.contains(r#"package="com.example.app""#));
+    assert!(xml.contains(r#"split="level_textures""#));
+    assert!(xml.contains("<dist:fast-follow />"));
+
+    let default_pack: AssetPack =
+        serde_json::from_value(json!({ "name": "music", "path": "music" })).unwrap();
+    assert_eq!(default_pack.delivery, AssetPackDelivery::InstallTime);
+    for name in ["base", "1levels", "level-textures", ""] {
+        let invalid = AssetPack {
+            name: name.to_string(),
+            ..default_pack.clone()
+        };
+        assert!(invalid.validate().is_err(), "{} should be rejected", name);
+    }
+    let levels = AssetPack {
+        name: "Levels".to_string(),
+        ..default_pack.clone()
+    };
+    assert!(AssetPack::validate_all(&[pack.clone(), default_pack.clone()]).is_ok());
+    assert!(AssetPack::validate_all(&[default_pack.clone(), default_pack.clone()]).is_err());
+    assert!(
+        AssetPack::validate_all(&[
+            levels.clone(),
+            AssetPack {
+                name: "levels".to_string(),
+                ..levels
+            }
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn test_texture_compression_config() {
+    let formats = vec!["astc".to_string(), "etc2".to_string()];
+    assert!(BundleSettings::default().to_config(&formats).is_err());
+    let settings = BundleSettings {
+        default_texture_format: Some("etc2".to_string()),
+        ..Default::default()
+    };
+    let config = settings.to_config(&formats).unwrap();
+    assert_eq!(
+        config["optimizations"]["splitsConfig"]["splitDimension"][3],
+        json!({
+            "value": "TEXTURE_COMPRESSION_FORMAT",
+            "negate": false,
+            "suffixStripping": { "enabled": true, "defaultSuffix": "etc2" },
+        })
+    );
+    let missing_default = BundleSettings {
+        default_texture_format: Some("pvrtc".to_string()),
+        ..Default::default()
+    };
+    assert!(missing_default.to_config(&formats).is_err());
+}
+
+#[test]
+fn test_gradle_manifest() {
+    let manifest = AndroidManifest {
+        package: "com.example.app".to_string(),
+        ..Default::default()
+    };
+    let xml = manifest.to_xml(true);
+    //gradle sets the package and sdk versions from build.gradle
+    assert!(!xml.contains("package="));
+    assert!(!xml.contains("<uses-sdk"));
+    assert!(xml.contains("android.intent.action.MAIN"));
+    let full = manifest.to_xml(false);
+    assert!(full.contains(r#"package="com.example.app""#));
+    assert!(full.contains("<uses-sdk"));
+
+    let debug = SigningKey {
+        path: r"C:\keys\debug.keystore".to_string(),
+        pass: "android".to_string(),
+        alias: "androiddebugkey".to_string(),
+    };
+    let properties = AndroidBuilder::gradle_keystore_properties(&[("debug", &debug)]);
+    assert!(properties.contains(r"debugStoreFile=C:\\keys\\debug.keystore"));
+    assert!(properties.contains("debugKeyAlias=androiddebugkey"));
+    assert!(!properties.contains("release"));
+    assert_eq!(
+        AndroidBuilder::groovy_string(r"it's $HOME\x"),
+        r"'it\'s $HOME\\x'"
+    );
+}
+
+#[test]
+fn test_debug_symbols_metadata_path() {
+    assert_eq!(
+        AndroidBuilder::debug_symbols_metadata_path(Path::new("arm64-v8a/libapp.so")),
+        "com.android.tools.build.debugsymbols/arm64-v8a/libapp.so.dbg"
+    );
+}
+
+#[test]
+fn test_config_rustflags() {
+    let configs: Vec<toml::Table> = [
+        r#"
+[build]
+rustflags = ["--cfg", "tokio_unstable"]
+"#,
+        r#"
+[build]
+rustflags = "-C target-cpu=native"
+[target.aarch64-linux-android]
+rustflags = ["-C", "opt-level=s", "-C", "link-arg=-L/opt/my libs"]
+"#,
+    ]
+    .iter()
+    .map(|text| text.parse().unwrap())
+    .collect();
+    assert_eq!(
+        AndroidBuilder::config_rustflags(&configs, &["build", "rustflags"]),
+        vec!["--cfg", "tokio_unstable", "-C", "target-cpu=native"]
+    );
+    assert_eq!(
+        AndroidBuilder::config_rustflags(
+            &configs,
+            &["target", "aarch64-linux-android", "rustflags"]
+        ),
+        vec!["-C", "opt-level=s", "-C", "link-arg=-L/opt/my libs"]
+    );
+    assert!(
+        AndroidBuilder::config_rustflags(
+            &configs,
+            &["target", "x86_64-linux-android", "rustflags"]
+        )
+        .is_empty()
+    );
+}
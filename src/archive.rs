@@ -0,0 +1,214 @@
+use crate::PistonError;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+//reproducible zip archives: sorted entries, 1980-01-01 timestamps and no dependency on a host zip binary
+#[derive(Default)]
+pub struct Archive {
+    //prepended to every entry name, e.g. "Payload/App.app"
+    prefix: String,
+    //entry names matching these file names or extensions are stored instead of deflated
+    stored_names: Vec<String>,
+    stored_extensions: Vec<String>,
+}
+
+impl Archive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_matches('/').to_string();
+        self
+    }
+
+    pub fn store_name(mut self, name: &str) -> Self {
+        self.stored_names.push(name.to_string());
+        self
+    }
+
+    pub fn store_extension(mut self, extension: &str) -> Self {
+        self.stored_extensions.push(extension.to_string());
+        self
+    }
+
+    //zip every file under src_dir into zip_path, replacing any existing archive
+    pub fn write_dir(&self, src_dir: &Path, zip_path: &Path) -> Result<(), PistonError> {
+        println!("zipping {} into {}", src_dir.display(), zip_path.display());
+        let mut files = Vec::new();
+        Self::collect(src_dir, &mut files)?;
+        let mut entries: Vec<(String, PathBuf)> = files
+            .into_iter()
+            .map(|path| (self.entry_name(src_dir, &path), path))
+            .collect();
+        entries.sort();
+
+        let file = File::create(zip_path).map_err(|e| PistonError::CreateFileError {
+            path: zip_path.to_path_buf(),
+            source: e,
+        })?;
+        let mut writer = ZipWriter::new(BufWriter::new(file));
+        for (name, path) in &entries {
+            //stream each file, asset packs can hold files of several GB
+            let mut input = File::open(path).map_err(|e| {
+                PistonError::ZipError(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            writer
+                .start_file(name.as_str(), self.options(name, path)?)
+                .map_err(|e| PistonError::ZipError(format!("{}: {}", name, e)))?;
+            io::copy(&mut input, &mut writer)
+                .map_err(|e| PistonError::ZipError(format!("{}: {}", name, e)))?;
+        }
+        writer
+            .finish()
+            .map_err(|e| PistonError::ZipError(format!("{}: {}", zip_path.display(), e)))?
+            .flush()
+            .map_err(|e| PistonError::ZipError(format!("{}: {}", zip_path.display(), e)))?;
+        Ok(())
+    }
+
+    //copy a single entry out of an existing archive, e.g. classes.jar from an .aar
+    pub fn extract_file(zip_path: &Path, name: &str, target: &Path) -> Result<(), PistonError> {
+        let file = File::open(zip_path).map_err(|e| {
+            PistonError::ZipError(format!("Failed to open {}: {}", zip_path.display(), e))
+        })?;
+        let mut archive = ZipArchive::new(file)
+            .map_err(|e| PistonError::ZipError(format!("{}: {}", zip_path.display(), e)))?;
+        let mut entry = archive.by_name(name).map_err(|e| {
+            PistonError::ZipError(format!("{} in {}: {}", name, zip_path.display(), e))
+        })?;
+        let mut output = File::create(target).map_err(|e| PistonError::CreateFileError {
+            path: target.to_path_buf(),
+            source: e,
+        })?;
+        io::copy(&mut entry, &mut output).map_err(|e| {
+            PistonError::ZipError(format!("{} in {}: {}", name, zip_path.display(), e))
+        })?;
+        Ok(())
+    }
+
+    //archive path of a file, always separated by '/' regardless of the host
+    fn entry_name(&self, src_dir: &Path, path: &Path) -> String {
+        let relative: Vec<String> = path
+            .strip_prefix(src_dir)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if self.prefix.is_empty() {
+            relative.join("/")
+        } else {
+            format!("{}/{}", self.prefix, relative.join("/"))
+        }
+    }
+
+    fn is_stored(&self, name: &str) -> bool {
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        self.stored_names.iter().any(|stored| stored == file_name)
+            || self
+                .stored_extensions
+                .iter()
+                .any(|extension| file_name.ends_with(&format!(".{}", extension)))
+    }
+
+    fn options(&self, name: &str, path: &Path) -> Result<SimpleFileOptions, PistonError> {
+        let method = if self.is_stored(name) {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        let metadata = fs::metadata(path).map_err(|e| {
+            PistonError::ZipError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .last_modified_time(DateTime::default())
+            //files of 4 GB and more need zip64 headers
+            .large_file(metadata.len() >= u32::MAX as u64);
+        //keep the executable bit, e.g. the binary inside an .app bundle
+        #[cfg(unix)]
+        let executable = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o111 != 0
+        };
+        #[cfg(not(unix))]
+        let executable = path.extension().is_none();
+        Ok(options.unix_permissions(if executable { 0o755 } else { 0o644 }))
+    }
+
+    //every file below dir, following symlinks like zip -r does
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), PistonError> {
+        for entry in fs::read_dir(dir).map_err(|e| PistonError::ReadDirError {
+            path: dir.to_path_buf(),
+            source: e,
+        })? {
+            let path = entry
+                .map_err(|e| PistonError::ReadDirError {
+                    path: dir.to_path_buf(),
+                    source: e,
+                })?
+                .path();
+            if path.is_dir() {
+                Self::collect(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_archive_entries() {
+    use std::io::Read;
+    let dir = std::env::temp_dir().join(format!("piston_archive_{}", std::process::id()));
+    let src = dir.join("src");
+    fs::create_dir_all(src.join("lib").join("arm64-v8a")).unwrap();
+    fs::write(src.join("resources.arsc"), "table").unwrap();
+    fs::write(src.join("lib").join("arm64-v8a").join("libapp.so"), "elf").unwrap();
+    fs::write(src.join("AndroidManifest.xml"), "<manifest/>").unwrap();
+
+    let archive = Archive::new()
+        .prefix("Payload/App.app/")
+        .store_name("resources.arsc")
+        .store_extension("so");
+    let first = dir.join("first.zip");
+    let second = dir.join("second.zip");
+    archive.write_dir(&src, &first).unwrap();
+    archive.write_dir(&src, &second).unwrap();
+    //identical inputs give byte for byte identical archives
+    assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+
+    Archive::extract_file(&first, "Payload/App.app/resources.arsc", &dir.join("table")).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("table")).unwrap(), "table");
+    assert!(Archive::extract_file(&first, "classes.jar", &dir.join("classes.jar")).is_err());
+
+    let mut zip = ZipArchive::new(File::open(&first).unwrap()).unwrap();
+    let names: Vec<String> = zip.file_names().map(|name| name.to_string()).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+    assert_eq!(
+        zip.by_name("Payload/App.app/resources.arsc")
+            .unwrap()
+            .compression(),
+        CompressionMethod::Stored
+    );
+    assert_eq!(
+        zip.by_name("Payload/App.app/lib/arm64-v8a/libapp.so")
+            .unwrap()
+            .compression(),
+        CompressionMethod::Stored
+    );
+    let mut manifest = String::new();
+    let mut entry = zip.by_name("Payload/App.app/AndroidManifest.xml").unwrap();
+    assert_eq!(entry.compression(), CompressionMethod::Deflated);
+    assert_eq!(entry.last_modified(), Some(DateTime::default()));
+    entry.read_to_string(&mut manifest).unwrap();
+    assert_eq!(manifest, "<manifest/>");
+    drop(entry);
+    fs::remove_dir_all(&dir).unwrap();
+}
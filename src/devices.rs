@@ -1,4 +1,5 @@
 use crate::error::PistonError;
+use crate::helper::Helper;
 use std::collections::HashMap;
 use std::path::Path;
 use std::process::Command;
@@ -12,8 +13,36 @@ pub struct IOSDevice {
 
 #[derive(Debug, Clone)]
 pub struct AndroidDevice {
-    // model: String,
     pub id: String,
+    //adb state: device, unauthorized, offline, ...
+    pub state: String,
+    pub model: Option<String>,
+    //ro.product.cpu.abilist, primary abi first
+    pub abis: Vec<String>,
+    pub sdk: Option<u32>,
+}
+
+impl AndroidDevice {
+    //only devices in the "device" state accept installs and shell commands
+    pub fn is_ready(&self) -> bool {
+        self.state == "device"
+    }
+
+    //rust target for the first abi, in the device's order of preference, that piston can build
+    pub fn target(&self) -> Result<String, PistonError> {
+        self.abis
+            .iter()
+            .find_map(|abi| match abi.as_str() {
+                "arm64-v8a" => Some("aarch64-linux-android"),
+                "x86_64" => Some("x86_64-linux-android"),
+                _ => None,
+            })
+            .map(|target| target.to_string())
+            .ok_or(PistonError::UnsupportedTargetError(format!(
+                "device {} supports the abis {:?}, piston builds arm64-v8a and x86_64",
+                self.id, self.abis
+            )))
+    }
 }
 
 #[derive(Debug)]
@@ -23,9 +52,11 @@ pub struct Devices {
 }
 
 impl Devices {
+    //reconnect is only set by list-devices, a stale address costs a full adb connect timeout
     pub fn list_devices(
         env_vars: HashMap<String, String>,
         silent: bool,
+        reconnect: bool,
     ) -> Result<Self, PistonError> {
         //new devices struct
         let mut devices = Devices {
@@ -33,10 +64,22 @@ impl Devices {
             android: Vec::new(),
         };
 
-        let sdk_path: Option<String> = env_vars.get("sdk_path").cloned();
-        let adb_path = format!("{}/platform-tools/adb", sdk_path.unwrap_or_default());
+        let adb_path = Self::adb_path(&env_vars);
         //query Android devices if adb_path is configured in .env
         if Path::new(&adb_path).exists() {
+            //reconnect remembered wireless devices so they show up in adb devices
+            let remembered = if reconnect {
+                Self::network_devices(&env_vars)
+            } else {
+                Vec::new()
+            };
+            for address in remembered {
+                match Self::connect_android(&adb_path, &address) {
+                    Ok(()) => {}
+                    Err(e) if !silent => println!("{}", e),
+                    Err(_) => {}
+                }
+            }
             devices.populate_android(adb_path)?;
         } else {
             println!("Android installation not found");
@@ -53,9 +96,103 @@ impl Devices {
         Ok(devices)
     }
 
+    //platform-tools/adb of the resolved sdk, may not exist when android is not installed
+    pub fn adb_path(env_vars: &HashMap<String, String>) -> String {
+        let sdk_path: Option<String> = Helper::get_sdk_path(env_vars).ok();
+        format!("{}/platform-tools/adb", sdk_path.unwrap_or_default())
+    }
+
+    //wireless devices remembered by `device connect`, as host:port
+    pub fn network_devices(env_vars: &HashMap<String, String>) -> Vec<String> {
+        env_vars
+            .get("android_network_devices")
+            .map(|devices| {
+                devices
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|device| !device.is_empty())
+                    .map(|device| device.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    //host:port ids are wireless adb connections, everything else is a usb serial
+    pub fn is_network_address(id: &str) -> bool {
+        id.rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+    }
+
+    //android 11+ wireless debugging, the pairing port and code come from the device's pairing dialog
+    pub fn pair_android(adb_path: &str, address: &str, code: &str) -> Result<(), PistonError> {
+        let output = Command::new(adb_path)
+            .arg("pair")
+            .arg(address)
+            .arg(code)
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(e.to_string()))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || !stdout.contains("Successfully paired") {
+            return Err(PistonError::ADBDevicesError(format!(
+                "Failed to pair with {}: {}{}",
+                address,
+                stdout.trim(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        println!("{}", stdout.trim());
+        Ok(())
+    }
+
+    //adb connect exits 0 even when the connection fails, so the output decides
+    pub fn connect_android(adb_path: &str, address: &str) -> Result<(), PistonError> {
+        let output = Command::new(adb_path)
+            .arg("connect")
+            .arg(address)
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(e.to_string()))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !stdout.contains("connected to") {
+            return Err(PistonError::ADBDevicesError(format!(
+                "Failed to connect to {}: {}{}",
+                address,
+                stdout.trim(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    //the device for "android" or no selector is the first ready one, explicit ids must be ready too
+    pub fn find_android(&self, selector: Option<&str>) -> Result<&AndroidDevice, PistonError> {
+        let device = match selector.map(str::trim) {
+            None | Some("android") => {
+                return self.android.iter().find(|device| device.is_ready()).ok_or(
+                    PistonError::ADBDevicesError("No ready Android device found".to_string()),
+                );
+            }
+            Some(id) => self.android.iter().find(|device| device.id == id).ok_or(
+                PistonError::ADBDevicesError(format!("Android device {} not found", id)),
+            )?,
+        };
+        if !device.is_ready() {
+            return Err(PistonError::ADBDevicesError(format!(
+                "Android device {} is {}{}",
+                device.id,
+                device.state,
+                if device.state == "unauthorized" {
+                    ", accept the USB debugging prompt on the device"
+                } else {
+                    ""
+                }
+            )));
+        }
+        Ok(device)
+    }
+
     pub fn populate_android(&mut self, adb_path: String) -> Result<(), PistonError> {
         //Run the command `adb devices`
-        let output = match Command::new(adb_path).arg("devices").output() {
+        let output = match Command::new(&adb_path).arg("devices").output() {
             Ok(o) => o,
             Err(e) => return Err(PistonError::ADBDevicesError(e.to_string())),
         };
@@ -74,13 +211,19 @@ impl Devices {
             let trimmed = line.trim();
             if !trimmed.is_empty() {
                 let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                if parts.len() == 2 && parts[1] == "device" {
-                    //add the device serial to the vector
-                    self.android.push(AndroidDevice {
-                        //TODO make this model field more dynamic for chipset architecture currently useless
-                        // model: "Android Device".to_string(),
+                if parts.len() == 2 {
+                    //add the device serial to the vector, with its properties when it is reachable
+                    let mut device = AndroidDevice {
                         id: parts[0].to_string(),
-                    });
+                        state: parts[1].to_string(),
+                        model: None,
+                        abis: Vec::new(),
+                        sdk: None,
+                    };
+                    if device.is_ready() {
+                        Self::query_android_props(&adb_path, &mut device)?;
+                    }
+                    self.android.push(device);
                 }
             }
         }
@@ -88,6 +231,32 @@ impl Devices {
         Ok(())
     }
 
+    fn query_android_props(adb_path: &str, device: &mut AndroidDevice) -> Result<(), PistonError> {
+        //one shell round trip, each getprop prints a single line
+        let output = Command::new(adb_path)
+            .arg("-s")
+            .arg(&device.id)
+            .arg("shell")
+            .arg("getprop ro.product.model; getprop ro.product.cpu.abilist; getprop ro.build.version.sdk")
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(e.to_string()))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines().map(str::trim);
+        device.model = lines
+            .next()
+            .filter(|model| !model.is_empty())
+            .map(|model| model.to_string());
+        device.abis = lines
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .filter(|abi| !abi.is_empty())
+            .map(|abi| abi.to_string())
+            .collect();
+        device.sdk = lines.next().and_then(|sdk| sdk.parse().ok());
+        Ok(())
+    }
+
     fn populate_ios(&mut self) -> Result<(), PistonError> {
         // Run the command `xcrun xctrace list devices`
         let output = Command::new("xcrun")
@@ -165,6 +334,16 @@ impl Devices {
                     println!();
                     println!("Device {}:", index + 1);
                     println!("id: {}", device.id);
+                    println!("State: {}", device.state);
+                    if let Some(model) = &device.model {
+                        println!("Model: {}", model);
+                    }
+                    if !device.abis.is_empty() {
+                        println!("ABIs: {}", device.abis.join(", "));
+                    }
+                    if let Some(sdk) = device.sdk {
+                        println!("SDK: {}", sdk);
+                    }
                 }
                 if !self.ios.is_empty() {
                     println!();
@@ -183,3 +362,24 @@ impl Devices {
         }
     }
 }
+
+#[test]
+fn test_network_devices() {
+    assert!(Devices::is_network_address("192.168.1.20:37099"));
+    assert!(Devices::is_network_address(
+        "adb-R5CT-abc._adb-tls-connect._tcp:5555"
+    ));
+    assert!(!Devices::is_network_address("R5CT80ABCDE"));
+    assert!(!Devices::is_network_address("emulator-5554"));
+    let env_vars = HashMap::from([(
+        "android_network_devices".to_string(),
+        "192.168.1.20:5555, ,10.0.0.2:40000".to_string(),
+    )]);
+    assert_eq!(
+        Devices::network_devices(&env_vars),
+        vec![
+            "192.168.1.20:5555".to_string(),
+            "10.0.0.2:40000".to_string()
+        ]
+    );
+}
@@ -0,0 +1,296 @@
+use crate::PistonError;
+use std::fs;
+use std::path::Path;
+
+const SHT_DYNAMIC: u32 = 6;
+const PT_LOAD: u32 = 1;
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+
+//minimal little endian ELF reader for inspecting the native libraries we bundle
+pub struct Elf {
+    data: Vec<u8>,
+    is_64: bool,
+}
+
+struct Section {
+    sh_type: u32,
+    offset: usize,
+    size: usize,
+    link: usize,
+    entsize: usize,
+}
+
+impl Elf {
+    pub fn open(path: &Path) -> Result<Self, PistonError> {
+        let data = fs::read(path).map_err(|e| {
+            PistonError::ElfParseError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        Self::parse(data)
+            .map_err(|e| PistonError::ElfParseError(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        if data.len() < 52 || &data[0..4] != b"\x7fELF" {
+            return Err("not an ELF file".to_string());
+        }
+        let is_64 = match data[4] {
+            1 => false,
+            2 => true,
+            class => return Err(format!("unknown ELF class {}", class)),
+        };
+        if data[5] != 1 {
+            return Err("big endian ELF files are not supported".to_string());
+        }
+        Ok(Elf { data, is_64 })
+    }
+
+    fn read(&self, offset: usize, len: usize) -> Result<u64, String> {
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(format!("read of {} bytes at {} out of bounds", len, offset))?;
+        let mut value: u64 = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            value |= (*byte as u64) << (8 * i);
+        }
+        Ok(value)
+    }
+
+    //base + index * size + field, failing instead of overflowing on malformed headers
+    fn offset(base: u64, index: usize, size: u64, field: usize) -> Result<usize, String> {
+        usize::try_from(base)
+            .ok()
+            .and_then(|base| {
+                let size = usize::try_from(size).ok()?;
+                base.checked_add(index.checked_mul(size)?)?
+                    .checked_add(field)
+            })
+            .ok_or(format!(
+                "offset {} + {} * {} + {} overflows",
+                base, index, size, field
+            ))
+    }
+
+    //a file offset or size field as usize
+    fn to_usize(value: u64) -> Result<usize, String> {
+        usize::try_from(value).map_err(|_| format!("value {} does not fit in usize", value))
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    //read a pointer sized field
+    fn word(&self, offset: usize) -> Result<u64, String> {
+        self.read(offset, if self.is_64 { 8 } else { 4 })
+    }
+
+    fn sections(&self) -> Result<Vec<Section>, String> {
+        let (shoff, shentsize, shnum) = if self.is_64 {
+            (
+                self.read(0x28, 8)?,
+                self.read(0x3A, 2)?,
+                self.read(0x3C, 2)?,
+            )
+        } else {
+            (
+                self.read(0x20, 4)?,
+                self.read(0x2E, 2)?,
+                self.read(0x30, 2)?,
+            )
+        };
+        let mut sections = Vec::new();
+        for i in 0..shnum as usize {
+            let field = |delta: usize, len: usize| {
+                self.read(Self::offset(shoff, i, shentsize, delta)?, len)
+            };
+            let section = if self.is_64 {
+                Section {
+                    sh_type: field(0x04, 4)? as u32,
+                    offset: Self::to_usize(field(0x18, 8)?)?,
+                    size: Self::to_usize(field(0x20, 8)?)?,
+                    link: Self::to_usize(field(0x28, 4)?)?,
+                    entsize: Self::to_usize(field(0x38, 8)?)?,
+                }
+            } else {
+                Section {
+                    sh_type: field(0x04, 4)? as u32,
+                    offset: Self::to_usize(field(0x10, 4)?)?,
+                    size: Self::to_usize(field(0x14, 4)?)?,
+                    link: Self::to_usize(field(0x18, 4)?)?,
+                    entsize: Self::to_usize(field(0x24, 4)?)?,
+                }
+            };
+            sections.push(section);
+        }
+        Ok(sections)
+    }
+
+    //p_align of every PT_LOAD segment
+    pub fn load_alignments(&self) -> Result<Vec<u64>, String> {
+        let (phoff, phentsize, phnum) = if self.is_64 {
+            (
+                self.read(0x20, 8)?,
+                self.read(0x36, 2)?,
+                self.read(0x38, 2)?,
+            )
+        } else {
+            (
+                self.read(0x1C, 4)?,
+                self.read(0x2A, 2)?,
+                self.read(0x2C, 2)?,
+            )
+        };
+        let mut alignments = Vec::new();
+        for i in 0..phnum as usize {
+            let field = |delta: usize, len: usize| {
+                self.read(Self::offset(phoff, i, phentsize, delta)?, len)
+            };
+            if field(0, 4)? as u32 != PT_LOAD {
+                continue;
+            }
+            alignments.push(if self.is_64 {
+                field(0x30, 8)?
+            } else {
+                field(0x1C, 4)?
+            });
+        }
+        Ok(alignments)
+    }
+
+    fn c_string(&self, offset: usize) -> Result<String, String> {
+        let bytes = self
+            .data
+            .get(offset..)
+            .ok_or(format!("string at {} out of bounds", offset))?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).to_string())
+    }
+
+    //shared libraries listed as DT_NEEDED in the dynamic section
+    pub fn needed_libs(&self) -> Result<Vec<String>, String> {
+        let sections = self.sections()?;
+        let dynamic = match sections.iter().find(|s| s.sh_type == SHT_DYNAMIC) {
+            Some(dynamic) => dynamic,
+            None => return Ok(Vec::new()),
+        };
+        let strtab = sections
+            .get(dynamic.link)
+            .ok_or("dynamic section links to a missing string table")?;
+        let word_size = if self.is_64 { 8 } else { 4 };
+        let entsize = if dynamic.entsize == 0 {
+            word_size * 2
+        } else {
+            dynamic.entsize
+        };
+        let mut needed = Vec::new();
+        for i in 0..dynamic.size / entsize {
+            let entry = Self::offset(dynamic.offset as u64, i, entsize as u64, 0)?;
+            let tag = self.word(entry)?;
+            if tag == DT_NULL {
+                break;
+            }
+            if tag == DT_NEEDED {
+                let name_entry = entry
+                    .checked_add(word_size)
+                    .ok_or("dynamic entry offset overflows")?;
+                let name_offset = Self::to_usize(self.word(name_entry)?)?;
+                let name = strtab
+                    .offset
+                    .checked_add(name_offset)
+                    .ok_or(format!("string offset {} overflows", name_offset))?;
+                needed.push(self.c_string(name)?);
+            }
+        }
+        Ok(needed)
+    }
+}
+
+#[test]
+fn test_needed_libs() {
+    fn test_elf64(needed: &[&str]) -> Vec<u8> {
+        //ELF header, then .dynstr, then .dynamic, then section headers [null, .dynstr, .dynamic]
+        let mut dynstr = vec![0u8];
+        let mut dynamic = Vec::new();
+        for name in needed {
+            dynamic.extend_from_slice(&DT_NEEDED.to_le_bytes());
+            dynamic.extend_from_slice(&(dynstr.len() as u64).to_le_bytes());
+            dynstr.extend_from_slice(name.as_bytes());
+            dynstr.push(0);
+        }
+        dynamic.extend_from_slice(&[0u8; 16]);
+        let dynstr_off = 64usize;
+        let dynamic_off = dynstr_off + dynstr.len();
+        let shoff = dynamic_off + dynamic.len();
+
+        let mut data = vec![0u8; 64];
+        data[0..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x28..0x30].copy_from_slice(&(shoff as u64).to_le_bytes());
+        data[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes());
+        data.extend_from_slice(&dynstr);
+        data.extend_from_slice(&dynamic);
+
+        let section = |sh_type: u32, offset: usize, size: usize, link: u32, entsize: u64| {
+            let mut sh = vec![0u8; 64];
+            sh[0x04..0x08].copy_from_slice(&sh_type.to_le_bytes());
+            sh[0x18..0x20].copy_from_slice(&(offset as u64).to_le_bytes());
+            sh[0x20..0x28].copy_from_slice(&(size as u64).to_le_bytes());
+            sh[0x28..0x2C].copy_from_slice(&link.to_le_bytes());
+            sh[0x38..0x40].copy_from_slice(&entsize.to_le_bytes());
+            sh
+        };
+        data.extend(section(0, 0, 0, 0, 0));
+        data.extend(section(3, dynstr_off, dynstr.len(), 0, 0));
+        data.extend(section(SHT_DYNAMIC, dynamic_off, dynamic.len(), 1, 16));
+        data
+    }
+
+    let elf = Elf::parse(test_elf64(&["libc++_shared.so", "liblog.so"])).unwrap();
+    assert_eq!(
+        elf.needed_libs().unwrap(),
+        vec!["libc++_shared.so".to_string(), "liblog.so".to_string()]
+    );
+    assert!(Elf::parse(b"not an elf file at all, definitely not one".repeat(2)).is_err());
+}
+
+#[test]
+fn test_load_alignments() {
+    //ELF64 header followed by two PT_LOAD and one PT_DYNAMIC program header
+    let mut data = vec![0u8; 64];
+    data[0..4].copy_from_slice(b"\x7fELF");
+    data[4] = 2;
+    data[5] = 1;
+    data[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+    data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+    data[0x38..0x3A].copy_from_slice(&3u16.to_le_bytes());
+    for (p_type, p_align) in [(PT_LOAD, 0x4000u64), (2, 8), (PT_LOAD, 0x1000)] {
+        let mut ph = vec![0u8; 56];
+        ph[0..4].copy_from_slice(&p_type.to_le_bytes());
+        ph[0x30..0x38].copy_from_slice(&p_align.to_le_bytes());
+        data.extend(ph);
+    }
+    let elf = Elf::parse(data).unwrap();
+    assert_eq!(elf.load_alignments().unwrap(), vec![0x4000, 0x1000]);
+}
+
+#[test]
+fn test_malformed_headers() {
+    //section and program header tables placed at the very end of the address space
+    let mut data = vec![0u8; 64];
+    data[0..4].copy_from_slice(b"\x7fELF");
+    data[4] = 2;
+    data[5] = 1;
+    data[0x20..0x28].copy_from_slice(&u64::MAX.to_le_bytes());
+    data[0x28..0x30].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+    data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+    data[0x38..0x3A].copy_from_slice(&2u16.to_le_bytes());
+    data[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+    data[0x3C..0x3E].copy_from_slice(&2u16.to_le_bytes());
+    let elf = Elf::parse(data).unwrap();
+    assert!(elf.load_alignments().is_err());
+    assert!(elf.needed_libs().is_err());
+}
@@ -88,12 +88,16 @@ pub enum PistonError {
 
     RunAPKError(String),
 
-    WhoAmIError(String),
-
     KeyToolError(String),
 
     APKSignerError(String),
 
+    ElfParseError(String),
+
+    PlayAuthError(String),
+
+    ZipError(String),
+
     UnsupportedOSError {
         os: String,
         target: String,
@@ -167,6 +171,11 @@ pub enum PistonError {
         e: String,
     },
 
+    PlayApiError {
+        endpoint: String,
+        e: String,
+    },
+
     Generic(String),
 }
 
@@ -284,9 +293,13 @@ impl fmt::Display for PistonError {
                 write!(f, "Error Installing APK with bundletool: {}", err)
             }
             PistonError::RunAPKError(err) => write!(f, "Error Running APK with ADB: {}", err),
-            PistonError::WhoAmIError(err) => write!(f, "Error running 'whoami': {}", err),
             PistonError::KeyToolError(err) => write!(f, "Error running 'keytool': {}", err),
             PistonError::APKSignerError(err) => write!(f, "Error running 'apksigner': {}", err),
+            PistonError::ElfParseError(err) => write!(f, "Error parsing ELF library: {}", err),
+            PistonError::ZipError(err) => write!(f, "Error writing zip archive: {}", err),
+            PistonError::PlayAuthError(err) => {
+                write!(f, "Error authenticating with Google Play: {}", err)
+            }
             PistonError::UnsupportedOSError { os, target, .. } => write!(
                 f,
                 "Host system: {:?} does not support the target: {:?}",
@@ -349,6 +362,11 @@ impl fmt::Display for PistonError {
                 "ASC API error at endpoint: {:?}, Error message: {}",
                 endpoint, e
             ),
+            PistonError::PlayApiError { endpoint, e } => write!(
+                f,
+                "Google Play API error at endpoint: {:?}, Error message: {}",
+                endpoint, e
+            ),
             PistonError::Generic(err) => write!(f, "Generic Error: {}", err),
         }
     }
@@ -10,7 +10,7 @@ use std::process::Command;
 
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 
 pub struct Helper {}
 
@@ -58,7 +58,8 @@ impl Helper {
         Ok(())
     }
 
-    pub fn sync_assets(src: &Path, tgt: &Path) -> Result<(), PistonError> {
+    //exclude lists directories under src that are left out of tgt, e.g. android asset packs
+    pub fn sync_assets(src: &Path, tgt: &Path, exclude: &[PathBuf]) -> Result<(), PistonError> {
         println!(
             "syncing assets source: {} with target: {}",
             src.display(),
@@ -83,15 +84,92 @@ impl Helper {
 
         println!("📦 Syncing assets: {:?} → {:?}", src, tgt);
 
-        Self::copy_updated_files(src, tgt)?;
-        Self::remove_stale_files(src, tgt)?;
+        Self::copy_updated_files(src, tgt, exclude)?;
+        Self::remove_stale_files(src, tgt, exclude)?;
 
         println!("✅ Assets synced (only changed files were updated)");
         Ok(())
     }
 
+    //texture compression formats targeted by <dir>#tcf_<format> directories below dir, as used by app bundles
+    pub fn texture_compression_formats(dir: &Path) -> Result<Vec<String>, PistonError> {
+        let mut groups: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        if dir.is_dir() {
+            Self::collect_texture_dirs(dir, &mut groups)?;
+        }
+        let mut formats: Vec<String> = groups.values().flatten().cloned().collect();
+        formats.sort();
+        formats.dedup();
+        //every targeted directory needs a variant in each format, otherwise some devices get no textures
+        for (group, group_formats) in &groups {
+            let missing: Vec<&str> = formats
+                .iter()
+                .filter(|format| !group_formats.contains(format))
+                .map(|format| format.as_str())
+                .collect();
+            if !missing.is_empty() {
+                return Err(PistonError::Generic(format!(
+                    "{} has no #tcf_ variant for: {}",
+                    group.display(),
+                    missing.join(", ")
+                )));
+            }
+        }
+        Ok(formats)
+    }
+
+    //groups directory variants by their path without the #tcf_ suffix
+    fn collect_texture_dirs(
+        dir: &Path,
+        groups: &mut HashMap<PathBuf, Vec<String>>,
+    ) -> Result<(), PistonError> {
+        for entry in read_dir(dir).map_err(|e| PistonError::ReadDirError {
+            path: dir.to_path_buf(),
+            source: e,
+        })? {
+            let entry = entry.map_err(|e| PistonError::ReadDirError {
+                path: dir.to_path_buf(),
+                source: e,
+            })?;
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some((base, format)) = name.split_once("#tcf_") {
+                //the aliases bundletool accepts for TEXTURE_COMPRESSION_FORMAT targeting
+                const FORMATS: [&str; 10] = [
+                    "astc",
+                    "etc2",
+                    "etc1_rgb8",
+                    "pvrtc",
+                    "s3tc",
+                    "dxt1",
+                    "atc",
+                    "latc",
+                    "3dc",
+                    "paletted",
+                ];
+                if !FORMATS.contains(&format) {
+                    return Err(PistonError::Generic(format!(
+                        "Unknown texture compression format {} in {}, expected one of: {}",
+                        format,
+                        path.display(),
+                        FORMATS.join(", ")
+                    )));
+                }
+                groups
+                    .entry(dir.join(base))
+                    .or_default()
+                    .push(format.to_string());
+            }
+            Self::collect_texture_dirs(&path, groups)?;
+        }
+        Ok(())
+    }
+
     //copy new or newer files
-    fn copy_updated_files(src: &Path, dst: &Path) -> Result<(), PistonError> {
+    fn copy_updated_files(src: &Path, dst: &Path, exclude: &[PathBuf]) -> Result<(), PistonError> {
         for entry in read_dir(src).map_err(|e| PistonError::ReadDirError {
             path: src.to_path_buf(),
             source: e,
@@ -102,6 +180,9 @@ impl Helper {
             })?;
             let src_path = entry.path();
             let dst_path = dst.join(entry.file_name());
+            if exclude.contains(&src_path) {
+                continue;
+            }
 
             if src_path.is_dir() {
                 fs::create_dir_all(&dst_path).map_err(|e| {
@@ -112,7 +193,7 @@ impl Helper {
                     ))
                 })?;
 
-                Self::copy_updated_files(&src_path, &dst_path)?;
+                Self::copy_updated_files(&src_path, &dst_path, exclude)?;
             } else {
                 let needs_copy = match (src_path.metadata(), dst_path.metadata()) {
                     (Ok(src_meta), Ok(dst_meta)) => {
@@ -156,7 +237,7 @@ impl Helper {
     }
 
     //delete files in target that no longer exist in source
-    fn remove_stale_files(src: &Path, dst: &Path) -> Result<(), PistonError> {
+    fn remove_stale_files(src: &Path, dst: &Path, exclude: &[PathBuf]) -> Result<(), PistonError> {
         for entry in read_dir(dst).map_err(|e| PistonError::ReadDirError {
             path: dst.to_path_buf(),
             source: e,
@@ -167,17 +248,19 @@ impl Helper {
             })?;
             let dst_path = entry.path();
             let corresponding_src = src.join(entry.file_name());
+            //excluded sources count as removed
+            let src_exists = corresponding_src.exists() && !exclude.contains(&corresponding_src);
 
             if dst_path.is_dir() {
-                if !corresponding_src.exists() {
+                if !src_exists {
                     remove_dir_all(&dst_path).map_err(|e| PistonError::RemoveSubdirError {
                         path: dst_path.clone(),
                         source: e,
                     })?;
                 } else {
-                    Self::remove_stale_files(&corresponding_src, &dst_path)?;
+                    Self::remove_stale_files(&corresponding_src, &dst_path, exclude)?;
                 }
-            } else if !corresponding_src.exists() {
+            } else if !src_exists {
                 remove_file(&dst_path).map_err(|e| PistonError::RemoveFileError {
                     path: dst_path.clone(),
                     source: e,
@@ -187,6 +270,28 @@ impl Helper {
         Ok(())
     }
 
+    //recursively collect every file under dir with the given extension, sorted for stable ordering
+    pub fn collect_files(dir: &Path, extension: &str) -> Result<Vec<PathBuf>, PistonError> {
+        let mut files = Vec::new();
+        for entry in read_dir(dir).map_err(|e| PistonError::ReadDirError {
+            path: dir.to_path_buf(),
+            source: e,
+        })? {
+            let entry = entry.map_err(|e| PistonError::ReadDirError {
+                path: dir.to_path_buf(),
+                source: e,
+            })?;
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(Self::collect_files(&path, extension)?);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some(extension) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
     // pub fn copy_dir_all(input: &Path, output: &Path) -> Result<(), PistonError> {
     //     if !output.exists() {
     //         create_dir_all(&output)
@@ -247,6 +352,33 @@ impl Helper {
         Ok(env_map)
     }
 
+    //set a single key in the project's .env, keeping every other line and comment as written
+    pub fn set_env_var(key: &str, value: &str) -> Result<(), PistonError> {
+        let path = std::env::current_dir()
+            .map_err(|e| PistonError::Generic(format!("Error getting working directory: {}", e)))?
+            .join(".env");
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        let mut found = false;
+        let mut lines: Vec<String> = contents
+            .lines()
+            .map(|line| {
+                let is_key = line
+                    .split_once('=')
+                    .is_some_and(|(k, _)| k.trim() == key && !line.trim_start().starts_with('#'));
+                if is_key && !found {
+                    found = true;
+                    format!("{}={}", key, value)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect();
+        if !found {
+            lines.push(format!("{}={}", key, value));
+        }
+        Self::write_file(&path, &format!("{}\n", lines.join("\n")))
+    }
+
     pub fn capitalize_first(s: &str) -> String {
         match s.get(0..1) {
             None => String::new(),
@@ -287,6 +419,130 @@ impl Helper {
         Ok(())
     }
 
+    //resize a png and mask everything outside the inscribed circle
+    pub fn round_png(input_name: &str, target_name: &str, size: u32) -> Result<(), PistonError> {
+        let img = image::open(input_name).map_err(|e| PistonError::OpenImageError {
+            path: PathBuf::from(input_name),
+            source: e,
+        })?;
+        let mut resized_img = imageops::resize(&img, size, size, imageops::FilterType::Lanczos3);
+        let radius = size as f32 / 2.0;
+        for (x, y, pixel) in resized_img.enumerate_pixels_mut() {
+            let dx = x as f32 + 0.5 - radius;
+            let dy = y as f32 + 0.5 - radius;
+            if dx * dx + dy * dy > radius * radius {
+                pixel[3] = 0;
+            }
+        }
+        resized_img.save(target_name).map_err(|e| {
+            PistonError::SaveImageError(format!("Failed to save {}: {}", target_name, e))
+        })?;
+        Ok(())
+    }
+
+    //resize a png to content_size and center it on a transparent canvas_size square
+    pub fn pad_png(
+        input_name: &str,
+        target_name: &str,
+        canvas_size: u32,
+        content_size: u32,
+    ) -> Result<(), PistonError> {
+        let img = image::open(input_name).map_err(|e| PistonError::OpenImageError {
+            path: PathBuf::from(input_name),
+            source: e,
+        })?;
+        let resized_img = imageops::resize(
+            &img,
+            content_size,
+            content_size,
+            imageops::FilterType::Lanczos3,
+        );
+        let mut canvas = image::RgbaImage::new(canvas_size, canvas_size);
+        let offset = (canvas_size.saturating_sub(content_size) / 2) as i64;
+        imageops::overlay(&mut canvas, &resized_img, offset, offset);
+        canvas.save(target_name).map_err(|e| {
+            PistonError::SaveImageError(format!("Failed to save {}: {}", target_name, e))
+        })?;
+        Ok(())
+    }
+
+    //center a png on a width x height canvas, filled with an android color literal or transparent
+    pub fn banner_png(
+        input_name: &str,
+        target_name: &str,
+        width: u32,
+        height: u32,
+        background: Option<&str>,
+    ) -> Result<(), PistonError> {
+        let img = image::open(input_name).map_err(|e| PistonError::OpenImageError {
+            path: PathBuf::from(input_name),
+            source: e,
+        })?;
+        //the icon takes up 3/4 of the banner height
+        let content_size = height * 3 / 4;
+        let resized_img = imageops::resize(
+            &img,
+            content_size,
+            content_size,
+            imageops::FilterType::Lanczos3,
+        );
+        let fill = background
+            .and_then(Self::hex_color_rgba)
+            .unwrap_or([0, 0, 0, 0]);
+        let mut canvas = image::RgbaImage::from_pixel(width, height, image::Rgba(fill));
+        imageops::overlay(
+            &mut canvas,
+            &resized_img,
+            (width.saturating_sub(content_size) / 2) as i64,
+            (height.saturating_sub(content_size) / 2) as i64,
+        );
+        canvas.save(target_name).map_err(|e| {
+            PistonError::SaveImageError(format!("Failed to save {}: {}", target_name, e))
+        })?;
+        Ok(())
+    }
+
+    //rgba of an android color literal, #RGB, #ARGB, #RRGGBB or #AARRGGBB
+    fn hex_color_rgba(value: &str) -> Option<[u8; 4]> {
+        if !Self::is_hex_color(value) {
+            return None;
+        }
+        let hex = &value[1..];
+        //expand the short forms to one byte per channel
+        let hex: String = if hex.len() <= 4 {
+            hex.chars().flat_map(|c| [c, c]).collect()
+        } else {
+            hex.to_string()
+        };
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            6 => Some([channel(0)?, channel(2)?, channel(4)?, 255]),
+            8 => Some([channel(2)?, channel(4)?, channel(6)?, channel(0)?]),
+            _ => None,
+        }
+    }
+
+    //true for android color literals such as #RGB, #RRGGBB or #AARRGGBB
+    pub fn is_hex_color(value: &str) -> bool {
+        match value.strip_prefix('#') {
+            Some(hex) => {
+                matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => false,
+        }
+    }
+
+    //create or overwrite a file with the given contents
+    pub fn write_file(path: &Path, contents: &str) -> Result<(), PistonError> {
+        let mut file = File::create(path).map_err(|e| PistonError::CreateFileError {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        file.write_all(contents.as_bytes())
+            .map_err(|e| PistonError::WriteFileError(format!("{}: {}", path.display(), e)))?;
+        Ok(())
+    }
+
     pub fn get_or_err<'a>(
         map: &'a HashMap<String, String>,
         key: &str,
@@ -297,13 +553,14 @@ impl Helper {
         )))
     }
 
-    pub fn get_host_platform(ndk_path: &str) -> Result<String, PistonError> {
-        let prebuilt_path = PathBuf::from(ndk_path).join("toolchains/llvm/prebuilt");
-
-        let entries = fs::read_dir(&prebuilt_path)
-            .map_err(|e| PistonError::BuildError(format!("Failed to read prebuilt dir: {}", e)))?;
+    //list the names of the subdirectories of dir
+    fn list_subdirs(dir: &Path) -> Result<Vec<String>, PistonError> {
+        let entries = fs::read_dir(dir).map_err(|e| PistonError::ReadDirError {
+            path: dir.to_path_buf(),
+            source: e,
+        })?;
 
-        let mut host_dirs = Vec::new();
+        let mut names = Vec::new();
         for entry in entries {
             let entry =
                 entry.map_err(|e| PistonError::BuildError(format!("Dir entry error: {}", e)))?;
@@ -313,60 +570,204 @@ impl Helper {
                 .is_dir()
             {
                 if let Some(name) = entry.file_name().to_str() {
-                    host_dirs.push(name.to_string());
+                    names.push(name.to_string());
                 }
             }
         }
+        Ok(names)
+    }
 
-        if host_dirs.is_empty() {
-            return Err(PistonError::BuildError(
-                "No host platform dir found in NDK prebuilt".to_string(),
-            ));
-        } else if host_dirs.len() > 1 {
-            // Warn or error; for now, take the first
-            eprintln!(
-                "Warning: Multiple host dirs found; using the first: {}",
-                host_dirs[0]
-            );
+    //sort key for sdk style versions like "34.0.0", "35.0.0-rc1" or "26.1.10909125"
+    //stable releases sort above pre-releases, then numeric components compare numerically
+    pub fn version_key(version: &str) -> (bool, Vec<u64>) {
+        let (numbers, pre_release) = match version.split_once('-') {
+            Some((numbers, _)) => (numbers, true),
+            None => (version, false),
+        };
+        let parts = numbers
+            .split('.')
+            .map(|part| part.parse::<u64>().unwrap_or(0))
+            .collect();
+        (!pre_release, parts)
+    }
+
+    //pick the pinned version if installed, otherwise the highest installed version
+    pub fn select_version(installed: &[String], pinned: Option<&str>) -> Option<String> {
+        match pinned {
+            Some(pinned) => installed.iter().find(|v| v.as_str() == pinned).cloned(),
+            None => installed
+                .iter()
+                .filter(|v| v.chars().next().is_some_and(|c| c.is_ascii_digit()))
+                .max_by_key(|v| Self::version_key(v))
+                .cloned(),
         }
+    }
 
-        Ok(host_dirs[0].clone())
+    pub fn get_host_platform(ndk_path: &str) -> Result<String, PistonError> {
+        let prebuilt_path = PathBuf::from(ndk_path).join("toolchains/llvm/prebuilt");
+        let mut host_dirs = Self::list_subdirs(&prebuilt_path)?;
+        host_dirs.sort();
+
+        //NDK host dirs are named <os>-<arch>, darwin ships as darwin-x86_64 for both architectures
+        let host_os = match std::env::consts::OS {
+            "macos" => "darwin",
+            os => os,
+        };
+        let exact = format!("{}-{}", host_os, std::env::consts::ARCH);
+        let fallback = format!("{}-x86_64", host_os);
+        host_dirs
+            .iter()
+            .find(|dir| **dir == exact)
+            .or_else(|| host_dirs.iter().find(|dir| **dir == fallback))
+            .or_else(|| {
+                host_dirs
+                    .iter()
+                    .find(|dir| dir.starts_with(&format!("{}-", host_os)))
+            })
+            .cloned()
+            .ok_or(PistonError::BuildError(format!(
+                "No NDK prebuilt toolchain for host {} found in {}, available: {:?}",
+                exact,
+                prebuilt_path.display(),
+                host_dirs
+            )))
     }
 
-    pub fn get_build_tools_version(sdk_path: &str) -> Result<String, PistonError> {
-        let prebuilt_path = PathBuf::from(sdk_path).join("build-tools");
+    //a pinned version is used as is, otherwise the highest installed version
+    pub fn get_build_tools_version(
+        sdk_path: &str,
+        pinned: Option<&str>,
+        target_sdk: u32,
+    ) -> Result<String, PistonError> {
+        let build_tools_path = PathBuf::from(sdk_path).join("build-tools");
+        let installed = Self::list_subdirs(&build_tools_path)?;
+
+        let version = Self::select_version(&installed, pinned).ok_or(match pinned {
+            Some(pinned) => PistonError::AndroidConfigError(format!(
+                "build_tools_version {} is not installed in {}, available: {:?}. Install it with sdkmanager \"build-tools;{}\"",
+                pinned,
+                build_tools_path.display(),
+                installed,
+                pinned
+            )),
+            None => PistonError::BuildError(format!(
+                "No build tools version found in {}",
+                build_tools_path.display()
+            )),
+        })?;
+        //newer build tools are backward compatible, only point out the mismatch
+        if pinned.is_none() && Self::build_tools_major(&version) > Some(target_sdk as u64) {
+            println!(
+                "⚠️  using build-tools {} for target_sdk_version {}, pin build_tools_version to use a matching release",
+                version, target_sdk
+            );
+        }
+        Ok(version)
+    }
 
-        let entries = fs::read_dir(&prebuilt_path)
-            .map_err(|e| PistonError::BuildError(format!("Failed to read prebuilt dir: {}", e)))?;
+    pub fn build_tools_major(version: &str) -> Option<u64> {
+        Self::version_key(version).1.first().copied()
+    }
 
-        let mut host_dirs = Vec::new();
-        for entry in entries {
-            let entry =
-                entry.map_err(|e| PistonError::BuildError(format!("Dir entry error: {}", e)))?;
-            if entry
-                .file_type()
-                .map_err(|e| PistonError::BuildError(format!("File type error: {}", e)))?
-                .is_dir()
-            {
-                if let Some(name) = entry.file_name().to_str() {
-                    host_dirs.push(name.to_string());
+    //resolve the SDK from .env sdk_path, falling back to ANDROID_HOME and ANDROID_SDK_ROOT
+    pub fn get_sdk_path(env_vars: &HashMap<String, String>) -> Result<String, PistonError> {
+        env_vars
+            .get("sdk_path")
+            .cloned()
+            .or_else(|| std::env::var("ANDROID_HOME").ok())
+            .or_else(|| std::env::var("ANDROID_SDK_ROOT").ok())
+            .filter(|path| !path.is_empty())
+            .ok_or(PistonError::AndroidConfigError(
+                "key 'sdk_path' not found in .env and neither ANDROID_HOME nor ANDROID_SDK_ROOT is set".to_string(),
+            ))
+    }
+
+    //resolve the NDK in order: .env ndk_path (when it matches a pinned ndk_version), pinned
+    //ndk_version inside <sdk>/ndk, ANDROID_NDK_HOME/ANDROID_NDK_ROOT, then the highest version inside <sdk>/ndk
+    pub fn get_ndk_path(
+        env_vars: &HashMap<String, String>,
+        sdk_path: &str,
+        pinned: Option<&str>,
+    ) -> Result<String, PistonError> {
+        let saved_path = env_vars.get("ndk_path");
+        let saved_version = saved_path.and_then(|path| Self::ndk_version(Path::new(path)));
+        if let Some(path) = saved_path {
+            match pinned {
+                None => return Ok(path.clone()),
+                Some(pinned) if saved_version.as_deref() == Some(pinned) => {
+                    return Ok(path.clone());
                 }
+                Some(pinned) => println!(
+                    "⚠️  ndk_path {} in .env is NDK {}, looking for the pinned ndk_version {} instead",
+                    path,
+                    saved_version.as_deref().unwrap_or("of unknown version"),
+                    pinned
+                ),
             }
         }
-
-        if host_dirs.is_empty() {
-            return Err(PistonError::BuildError(
-                "No build tools version found in SDK prebuilt".to_string(),
-            ));
-        } else if host_dirs.len() > 1 {
-            // Warn or error; for now, take the first
-            eprintln!(
-                "Warning: Multiple host dirs found; using the first: {}",
-                host_dirs[0]
-            );
+        let ndk_dir = PathBuf::from(sdk_path).join("ndk");
+        let installed = Self::list_subdirs(&ndk_dir).unwrap_or_default();
+        if let Some(pinned) = pinned {
+            return Self::select_version(&installed, Some(pinned))
+                .map(|version| ndk_dir.join(version).display().to_string())
+                .ok_or(PistonError::AndroidConfigError(format!(
+                    "ndk_version {} is not installed in {}{}, available: {:?}. Install it with sdkmanager \"ndk;{}\"",
+                    pinned,
+                    ndk_dir.display(),
+                    saved_path
+                        .map(|path| format!(" and ndk_path {} in .env is a different version", path))
+                        .unwrap_or_default(),
+                    installed,
+                    pinned
+                )));
+        }
+        if let Some(path) = std::env::var("ANDROID_NDK_HOME")
+            .ok()
+            .or_else(|| std::env::var("ANDROID_NDK_ROOT").ok())
+            .filter(|path| !path.is_empty())
+        {
+            return Ok(path);
         }
+        Self::select_version(&installed, None)
+            .map(|version| ndk_dir.join(version).display().to_string())
+            .ok_or(PistonError::AndroidConfigError(format!(
+                "No NDK found. Set ndk_path in .env, ANDROID_NDK_HOME, or install one into {}",
+                ndk_dir.display()
+            )))
+    }
+
+    //Pkg.Revision from <ndk>/source.properties, e.g. 26.1.10909125
+    pub fn ndk_version(ndk_path: &Path) -> Option<String> {
+        let properties = fs::read_to_string(ndk_path.join("source.properties")).ok()?;
+        Self::parse_pkg_revision(&properties)
+    }
+
+    pub fn parse_pkg_revision(properties: &str) -> Option<String> {
+        properties.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "Pkg.Revision").then(|| value.trim().to_string())
+        })
+    }
 
-        Ok(host_dirs[0].clone())
+    //locate platforms/android-<target_sdk>/android.jar, failing with the installed platforms otherwise
+    pub fn get_android_jar(sdk_path: &str, target_sdk: u32) -> Result<PathBuf, PistonError> {
+        let platforms = PathBuf::from(sdk_path).join("platforms");
+        let android_jar = platforms
+            .join(format!("android-{}", target_sdk))
+            .join("android.jar");
+        if android_jar.exists() {
+            return Ok(android_jar);
+        }
+        let mut installed = Self::list_subdirs(&platforms).unwrap_or_default();
+        installed
+            .sort_by_key(|platform| Self::version_key(platform.trim_start_matches("android-")));
+        Err(PistonError::AndroidConfigError(format!(
+            "android.jar for target_sdk_version {} not found at {}, installed platforms: {:?}. Install it with sdkmanager \"platforms;android-{}\"",
+            target_sdk,
+            android_jar.display(),
+            installed,
+            target_sdk
+        )))
     }
 
     pub fn get_lib_name(metadata: &Metadata) -> Result<String, PistonError> {
@@ -388,6 +789,27 @@ impl Helper {
         Ok(lib_name)
     }
 
+    //name of the root package's rust library target, if it has one that other crates can depend on
+    pub fn get_rlib_name(metadata: &Metadata) -> Result<String, PistonError> {
+        let root_package = metadata.root_package().ok_or(PistonError::CargoParseError(
+            "no root package found in metadata".to_string(),
+        ))?;
+        root_package
+            .targets
+            .iter()
+            .find(|target| {
+                target
+                    .kind
+                    .iter()
+                    .any(|k| *k == TargetKind::Lib || *k == TargetKind::RLib)
+            })
+            .map(|target| target.name.replace("-", "_"))
+            .ok_or(PistonError::CargoParseError(format!(
+                "package {} has no lib target, add a src/lib.rs exposing the android entry function",
+                root_package.name
+            )))
+    }
+
     pub fn get_icon_path(metadata: &Metadata, cwd: &PathBuf) -> String {
         let res = metadata
             .root_package()
@@ -469,3 +891,95 @@ impl Helper {
             .unwrap_or(default)
     }
 }
+
+#[test]
+fn test_select_version() {
+    let installed: Vec<String> = ["30.0.3", "34.0.0", "35.0.0-rc1", "9.0.0", "docs"]
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert_eq!(
+        Helper::select_version(&installed, None),
+        Some("34.0.0".to_string())
+    );
+    assert_eq!(
+        Helper::select_version(&installed, Some("30.0.3")),
+        Some("30.0.3".to_string())
+    );
+    assert_eq!(Helper::select_version(&installed, Some("33.0.0")), None);
+}
+
+#[test]
+fn test_ndk_and_build_tools_versions() {
+    assert_eq!(
+        Helper::parse_pkg_revision("Pkg.Desc = Android NDK\nPkg.Revision = 26.1.10909125\n"),
+        Some("26.1.10909125".to_string())
+    );
+    assert_eq!(Helper::parse_pkg_revision("Pkg.Desc = Android NDK"), None);
+    let installed: Vec<String> = ["33.0.2", "36.0.0-rc1", "35.0.0", "34.0.0"]
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    //the highest stable release wins even above the target sdk
+    assert_eq!(
+        Helper::select_version(&installed, None),
+        Some("35.0.0".to_string())
+    );
+    assert_eq!(Helper::build_tools_major("35.0.0"), Some(35));
+}
+
+#[test]
+fn test_hex_color_rgba() {
+    assert_eq!(
+        Helper::hex_color_rgba("#1E88E5"),
+        Some([0x1E, 0x88, 0xE5, 255])
+    );
+    assert_eq!(
+        Helper::hex_color_rgba("#80FFFFFF"),
+        Some([255, 255, 255, 0x80])
+    );
+    assert_eq!(Helper::hex_color_rgba("#F00"), Some([255, 0, 0, 255]));
+    assert_eq!(Helper::hex_color_rgba("assets/bg.png"), None);
+}
+
+#[test]
+fn test_sync_assets_exclude() {
+    let dir = std::env::temp_dir().join(format!("piston-sync-{}", std::process::id()));
+    let src = dir.join("assets");
+    let tgt = dir.join("base").join("assets");
+    create_dir_all(src.join("levels")).unwrap();
+    create_dir_all(src.join("ui")).unwrap();
+    fs::write(src.join("levels").join("1.bin"), "level").unwrap();
+    fs::write(src.join("ui").join("font.ttf"), "font").unwrap();
+    Helper::sync_assets(&src, &tgt, &[]).unwrap();
+    assert!(tgt.join("levels").join("1.bin").exists());
+    //previously synced files of a now excluded directory are removed
+    Helper::sync_assets(&src, &tgt, &[src.join("levels")]).unwrap();
+    assert!(!tgt.join("levels").exists());
+    assert!(tgt.join("ui").join("font.ttf").exists());
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_texture_compression_formats() {
+    let dir = std::env::temp_dir().join(format!("piston-tcf-{}", std::process::id()));
+    create_dir_all(dir.join("textures#tcf_astc")).unwrap();
+    create_dir_all(dir.join("textures#tcf_etc2")).unwrap();
+    create_dir_all(dir.join("ui")).unwrap();
+    assert_eq!(
+        Helper::texture_compression_formats(&dir).unwrap(),
+        vec!["astc".to_string(), "etc2".to_string()]
+    );
+    //a second targeted directory missing the etc2 variant
+    create_dir_all(dir.join("ui").join("icons#tcf_astc")).unwrap();
+    assert!(Helper::texture_compression_formats(&dir).is_err());
+    remove_dir_all(dir.join("ui")).unwrap();
+    create_dir_all(dir.join("textures#tcf_bc7")).unwrap();
+    assert!(Helper::texture_compression_formats(&dir).is_err());
+    remove_dir_all(&dir).unwrap();
+    assert!(
+        Helper::texture_compression_formats(&dir)
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -1,5 +1,6 @@
 use crate::Helper;
 use crate::PistonError;
+use crate::archive::Archive;
 use crate::asc::{AscApiKey, AscClient};
 use crate::devices::IOSDevice;
 use cargo_metadata::{Metadata, MetadataCommand};
@@ -564,54 +565,10 @@ impl IOSBuilder {
                     source: e,
                 })?;
             }
-            //remove any existing payload dir and its contents
-            let payload_path = parent.join("Payload");
-            if payload_path.exists() {
-                let _ = fs::remove_dir_all(&payload_path);
-            }
-            //create ~/Payload dir
-            create_dir_all(&payload_path).map_err(|e| PistonError::CreateDirAllError {
-                path: payload_path.to_path_buf(),
-                source: e,
-            })?;
-            //recursively copy app bundle contents to payload dir
-            let dest = payload_path.join(format!("{}.app", &capitalized));
-            let status = Command::new("cp")
-                .arg("-r")
-                .arg(&output_path)
-                .arg(&dest)
-                .output()
-                .map_err(|e| PistonError::CopyFileError {
-                    input_path: output_path.clone().to_path_buf(),
-                    output_path: dest.clone().to_path_buf(),
-                    source: e,
-                })?;
-
-            if !status.status.success() {
-                return Err(PistonError::Generic(format!(
-                    "error copying {} to {} Error message: {}",
-                    output_path.clone().display(),
-                    dest.clone().display(),
-                    String::from_utf8_lossy(&status.stderr)
-                )));
-            }
-
-            let status = Command::new("zip")
-                .arg("-r")
-                .arg(&ipa_path)
-                .arg("Payload")
-                .current_dir(parent)
-                .output()
-                .map_err(|e| PistonError::Generic(format!("Error zipping payload: {}", e)))?;
-
-            if !status.status.success() {
-                return Err(PistonError::Generic(format!(
-                    "Error zipping payload: {}",
-                    String::from_utf8_lossy(&status.stderr)
-                )));
-            }
-            //cleanup temp payload dir
-            let _ = std::fs::remove_dir_all(payload_path);
+            //zip the app bundle as Payload/<App>.app
+            Archive::new()
+                .prefix(&format!("Payload/{}.app", &capitalized))
+                .write_dir(&output_path, &ipa_path)?;
 
             println!("Your app is available at: {:?}", &ipa_path.display());
         }
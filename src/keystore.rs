@@ -0,0 +1,381 @@
+use crate::error::PistonError;
+use std::collections::HashMap;
+use std::fs::create_dir_all;
+use std::path::Path;
+use std::process::{Command, Output};
+
+//keystore, password and alias used to sign bundles and the apks extracted from them
+#[derive(Clone)]
+pub struct SigningKey {
+    pub path: String,
+    pub pass: String,
+    pub alias: String,
+}
+
+//an alias of a keystore as reported by keytool -list -v
+#[derive(Debug, Default, PartialEq)]
+pub struct KeystoreEntry {
+    pub alias: String,
+    pub entry_type: String,
+    pub owner: String,
+    pub valid_until: String,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+//the release or debug keystore configured in the .env, with the keytool operations piston needs
+pub struct Keystore {
+    pub key: SigningKey,
+    release: bool,
+    dname: String,
+}
+
+impl Keystore {
+    pub fn from_hm(env_vars: &HashMap<String, String>, release: bool) -> Result<Self, PistonError> {
+        //default keystores live in ~/.android, shared with android studio and gradle
+        let default_path = |file: &str| {
+            std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .map(|home| {
+                    Path::new(&home)
+                        .join(".android")
+                        .join(file)
+                        .display()
+                        .to_string()
+                })
+                .map_err(|_| {
+                    PistonError::AndroidConfigError(format!(
+                        "neither HOME nor USERPROFILE is set, set the path of {} in .env",
+                        file
+                    ))
+                })
+        };
+        //allow .env to override default key_path and key_pass and key_alias if it exists
+        let key = if release {
+            SigningKey {
+                path: match env_vars.get("aab_release_key") {
+                    Some(path) => path.clone(),
+                    None => default_path("release.keystore")?,
+                },
+                pass: env_vars
+                    .get("aab_key_pass")
+                    .cloned()
+                    .unwrap_or("piston".to_string()),
+                alias: env_vars
+                    .get("aab_key_alias")
+                    .cloned()
+                    .unwrap_or("release-key".to_string()),
+            }
+        } else {
+            //debug builds use the standard android debug keystore shared with other android tooling
+            SigningKey {
+                path: match env_vars.get("debug_keystore") {
+                    Some(path) => path.clone(),
+                    None => default_path("debug.keystore")?,
+                },
+                pass: "android".to_string(),
+                alias: "androiddebugkey".to_string(),
+            }
+        };
+        //debug keys follow the android sdk convention rather than the user's dname metadata
+        let dname = if release {
+            //allow .env to ovverride default dname metadata if provided
+            let field = |name: &str, default: &str| {
+                env_vars.get(name).cloned().unwrap_or(default.to_string())
+            };
+            let country: String = env_vars
+                .get("country")
+                .filter(|s| s.trim().len() == 2)
+                .map(|s| s.trim().to_uppercase().to_string())
+                .unwrap_or_else(|| "US".to_string());
+            format!(
+                "CN={}, OU={}, O={}, L={}, S={}, C={}",
+                field("common_name", "Unknown"),
+                field("org_unit", "Development"),
+                field("org", "Unknown"),
+                field("locality", "Unknown"),
+                field("state", "Unknown"),
+                country
+            )
+        } else {
+            "CN=Android Debug, O=Android, C=US".to_string()
+        };
+        Ok(Keystore {
+            key,
+            release,
+            dname,
+        })
+    }
+
+    pub fn kind(&self) -> &'static str {
+        if self.release { "release" } else { "debug" }
+    }
+
+    //use another alias of the same keystore, e.g. a separate upload key
+    pub fn with_alias(mut self, alias: Option<String>) -> Self {
+        if let Some(alias) = alias {
+            self.key.alias = alias;
+        }
+        self
+    }
+
+    //create the key on record if the keystore or the alias does not exist yet
+    pub fn ensure(&self) -> Result<(), PistonError> {
+        if Path::new(&self.key.path).exists() && self.has_alias()? {
+            println!("signing key found at: {}", self.key.path);
+            return Ok(());
+        }
+        self.create()
+    }
+
+    pub fn create(&self) -> Result<(), PistonError> {
+        //proceed to key creation, state the reason for the user
+        let kind = self.kind();
+        println!(
+            "creating {} key at path: {} with the alias: {}",
+            kind, self.key.path, self.key.alias
+        );
+        //check if .android exists, if not create
+        if let Some(parent) = Path::new(&self.key.path).parent() {
+            create_dir_all(parent).map_err(|e| PistonError::CreateDirAllError {
+                path: Path::new(&self.key.path).to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        //create the key with keytool
+        let output = self
+            .keytool()
+            .arg("-genkeypair")
+            .arg("-v")
+            .arg("-keystore")
+            .arg(&self.key.path)
+            .arg("-storepass")
+            .arg(&self.key.pass)
+            .arg("-keypass")
+            .arg(&self.key.pass)
+            .arg("-alias")
+            .arg(&self.key.alias)
+            .arg("-keyalg")
+            .arg("RSA")
+            .arg("-keysize")
+            .arg("2048")
+            .arg("-validity")
+            .arg("10000")
+            .arg("-dname")
+            .arg(&self.dname)
+            .output()
+            .map_err(|e| {
+                PistonError::KeyToolError(format!(
+                    "Failed to generate {} key with keytool: {}",
+                    kind, e
+                ))
+            })?;
+
+        if !output.status.success() {
+            return Err(PistonError::KeyToolError(format!(
+                "Failed to generate {} key: {}{}",
+                kind,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        println!("{} key successfully created at: {}", kind, self.key.path);
+        Ok(())
+    }
+
+    //verify the key alias on record exists by querying the keystore
+    pub fn has_alias(&self) -> Result<bool, PistonError> {
+        let output = self.list(None)?;
+        if !output.status.success() {
+            return Ok(false);
+        }
+        Ok(
+            Self::parse_entries(&String::from_utf8_lossy(&output.stdout))
+                .iter()
+                .any(|entry| entry.alias == self.key.alias),
+        )
+    }
+
+    //every alias in the keystore, not only the one on record
+    pub fn entries(&self) -> Result<Vec<KeystoreEntry>, PistonError> {
+        let output = self.checked(self.list(None)?)?;
+        Ok(Self::parse_entries(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    //fingerprints of the alias on record, as requested by firebase, maps and play app signing
+    pub fn entry(&self) -> Result<KeystoreEntry, PistonError> {
+        let output = self.checked(self.list(Some(&self.key.alias))?)?;
+        Self::parse_entries(&String::from_utf8_lossy(&output.stdout))
+            .into_iter()
+            .next()
+            .ok_or(PistonError::KeyToolError(format!(
+                "alias {} not found in {}",
+                self.key.alias, self.key.path
+            )))
+    }
+
+    //pem encoded certificate of the alias on record, e.g. the upload certificate for play app signing
+    pub fn export_cert(&self, output_path: &Path) -> Result<(), PistonError> {
+        if let Some(parent) = output_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            create_dir_all(parent).map_err(|e| PistonError::CreateDirAllError {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+        let output = self
+            .keytool()
+            .arg("-exportcert")
+            .arg("-rfc")
+            .arg("-keystore")
+            .arg(&self.key.path)
+            .arg("-storepass")
+            .arg(&self.key.pass)
+            .arg("-alias")
+            .arg(&self.key.alias)
+            .arg("-file")
+            .arg(output_path)
+            .output()
+            .map_err(|e| {
+                PistonError::KeyToolError(format!("Failed to export certificate: {}", e))
+            })?;
+        self.checked(output)?;
+        println!(
+            "certificate for {} exported to: {}",
+            self.key.alias,
+            output_path.display()
+        );
+        Ok(())
+    }
+
+    //keytool localizes its output, parsing relies on the english labels
+    fn keytool(&self) -> Command {
+        let mut command = Command::new("keytool");
+        command.arg("-J-Duser.language=en");
+        command
+    }
+
+    fn list(&self, alias: Option<&str>) -> Result<Output, PistonError> {
+        let mut command = self.keytool();
+        command
+            .arg("-list")
+            .arg("-v")
+            .arg("-keystore")
+            .arg(&self.key.path)
+            .arg("-storepass")
+            .arg(&self.key.pass);
+        if let Some(alias) = alias {
+            command.arg("-alias").arg(alias);
+        }
+        command.output().map_err(|e| {
+            PistonError::KeyToolError(format!("Failed to list keystore contents: {}", e))
+        })
+    }
+
+    fn checked(&self, output: Output) -> Result<Output, PistonError> {
+        if !output.status.success() {
+            //keytool reports most errors on stdout
+            return Err(PistonError::KeyToolError(format!(
+                "{}: {}{}",
+                self.key.path,
+                String::from_utf8_lossy(&output.stdout).trim(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output)
+    }
+
+    //only the first certificate of each chain is kept, it is the one the alias signs with
+    fn parse_entries(stdout: &str) -> Vec<KeystoreEntry> {
+        let mut entries: Vec<KeystoreEntry> = Vec::new();
+        for line in stdout.lines().map(str::trim) {
+            if let Some(alias) = line.strip_prefix("Alias name:") {
+                entries.push(KeystoreEntry {
+                    alias: alias.trim().to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            let Some(entry) = entries.last_mut() else {
+                continue;
+            };
+            let (label, value) = match line.split_once(':') {
+                Some((label, value)) => (label.trim(), value.trim()),
+                None => continue,
+            };
+            let field = match label {
+                "Entry type" => &mut entry.entry_type,
+                "Owner" => &mut entry.owner,
+                "SHA1" => &mut entry.sha1,
+                "SHA256" => &mut entry.sha256,
+                "Valid from" => {
+                    if entry.valid_until.is_empty()
+                        && let Some((_, until)) = value.split_once("until:")
+                    {
+                        entry.valid_until = until.trim().to_string();
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if field.is_empty() {
+                *field = value.to_string();
+            }
+        }
+        entries
+    }
+}
+
+#[test]
+fn test_parse_keystore_entries() {
+    let stdout = "Keystore type: PKCS12
+Keystore provider: SUN
+
+Your keystore contains 2 entries
+
+Alias name: release-key
+Creation date: Oct 19, 2026
+Entry type: PrivateKeyEntry
+Certificate chain length: 1
+Certificate[1]:
+Owner: CN=Unknown, OU=Development, O=Unknown, L=Unknown, ST=Unknown, C=US
+Issuer: CN=Unknown, OU=Development, O=Unknown, L=Unknown, ST=Unknown, C=US
+Serial number: 2620251c147a0341
+Valid from: Mon Oct 19 07:40:12 UTC 2026 until: Wed Mar 06 07:40:12 UTC 2054
+Certificate fingerprints:
+\t SHA1: 09:1B:CE:BA:74:7C:64:AE:AD:A8:67:FA:89:74:54:9C:DE:BD:94:0A
+\t SHA256: 41:A7:81:A8:45:99:B2:45:8D:50:9D:72:F7:78:A7:41:66:49:5D:1A:56:8A:CD:97:B8:9E:75:5C:46:FA:21:33
+Signature algorithm name: SHA256withRSA
+
+
+*******************************************
+*******************************************
+
+
+Alias name: upload
+Creation date: Oct 19, 2026
+Entry type: PrivateKeyEntry
+Certificate chain length: 1
+Certificate[1]:
+Owner: CN=Upload
+Certificate fingerprints:
+\t SHA1: 49:5A:5F:FF:ED:CB:3F:44:92:9A:99:F6:6D:11:E3:C1:66:EE:65:64
+\t SHA256: 4F:97:34:62:C2:68:04:8F:ED:0A:CB:DF:F6:B9:E2:1F:AA:2E:AA:0C:AF:F5:04:B2:09:47:2B:8F:F1:06:06:79
+";
+    let entries = Keystore::parse_entries(stdout);
+    assert_eq!(
+        entries.iter().map(|e| e.alias.as_str()).collect::<Vec<_>>(),
+        vec!["release-key", "upload"]
+    );
+    assert_eq!(entries[0].entry_type, "PrivateKeyEntry");
+    assert_eq!(entries[0].valid_until, "Wed Mar 06 07:40:12 UTC 2054");
+    assert_eq!(
+        entries[0].sha1,
+        "09:1B:CE:BA:74:7C:64:AE:AD:A8:67:FA:89:74:54:9C:DE:BD:94:0A"
+    );
+    assert_eq!(entries[1].owner, "CN=Upload");
+    assert!(entries[1].sha256.starts_with("4F:97:34"));
+}
@@ -0,0 +1,317 @@
+use crate::error::PistonError;
+use crate::helper::Helper;
+use crate::symbolicate::Symbolizer;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, IsTerminal};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+//how often the app's pid is polled while waiting for it to (re)start
+const PID_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+//streams the logs of a single android app, reattaching whenever its process restarts
+pub struct Logcat {
+    adb_path: String,
+    device_id: String,
+    package: String,
+    level: char,
+    color: bool,
+    symbolizer: Option<Symbolizer>,
+}
+
+impl Logcat {
+    pub fn start(
+        env_vars: &HashMap<String, String>,
+        device_id: &str,
+        package: &str,
+        level: &str,
+        symbolizer: Option<Symbolizer>,
+    ) -> Result<(), PistonError> {
+        let sdk_path: String = Helper::get_sdk_path(env_vars)?;
+        let logcat = Logcat {
+            adb_path: format!("{}/platform-tools/adb", sdk_path),
+            device_id: device_id.to_string(),
+            package: package.to_string(),
+            level: Self::parse_level(level)?,
+            //honor https://no-color.org and avoid escape codes when piped
+            color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+            symbolizer,
+        };
+        logcat.follow()
+    }
+
+    //accepts logcat's single letter priorities or their full names
+    fn parse_level(level: &str) -> Result<char, PistonError> {
+        match level.to_lowercase().as_str() {
+            "v" | "verbose" => Ok('V'),
+            "d" | "debug" => Ok('D'),
+            "i" | "info" => Ok('I'),
+            "w" | "warn" | "warning" => Ok('W'),
+            "e" | "error" => Ok('E'),
+            "f" | "fatal" => Ok('F'),
+            other => Err(PistonError::Generic(format!(
+                "Unknown log level: {}, expected one of verbose, debug, info, warn, error or fatal",
+                other
+            ))),
+        }
+    }
+
+    fn follow(&self) -> Result<(), PistonError> {
+        println!(
+            "streaming logs for {} on device {} at level {}, press ctrl-c to stop",
+            self.package, self.device_id, self.level
+        );
+        let mut waiting_logged = false;
+        loop {
+            let pid = match self.pid()? {
+                Some(pid) => pid,
+                None => {
+                    if !waiting_logged {
+                        println!("waiting for {} to start...", self.package);
+                        waiting_logged = true;
+                    }
+                    thread::sleep(PID_POLL_INTERVAL);
+                    continue;
+                }
+            };
+            waiting_logged = false;
+            let attached_at = self.device_time()?;
+            println!("--- attached to {} (pid {}) ---", self.package, pid);
+            let mut child = self.spawn_logcat(&pid)?;
+            let stdout = child.stdout.take().ok_or(PistonError::Generic(
+                "Failed to capture adb logcat output".to_string(),
+            ))?;
+            let color = self.color;
+            let reader = thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    println!("{}", Self::colorize(&line, color));
+                }
+            });
+            //logcat --pid keeps running after the process dies, so watch the pid ourselves
+            while self.pid()?.as_deref() == Some(pid.as_str()) {
+                thread::sleep(PID_POLL_INTERVAL);
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = reader.join();
+            println!("--- {} (pid {}) exited ---", self.package, pid);
+            self.print_crash(&pid, &attached_at)?;
+        }
+    }
+
+    //pid of the app's main process, none if it is not running
+    fn pid(&self) -> Result<Option<String>, PistonError> {
+        let output = Command::new(&self.adb_path)
+            .arg("-s")
+            .arg(&self.device_id)
+            .arg("shell")
+            .arg("pidof")
+            .arg("-s")
+            .arg(&self.package)
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(format!("Failed to run pidof: {}", e)))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .split_whitespace()
+            .next()
+            .filter(|pid| pid.chars().all(|c| c.is_ascii_digit()))
+            .map(|pid| pid.to_string()))
+    }
+
+    //device clock in the format logcat -t accepts, so the crash buffer can be read from attach time on
+    fn device_time(&self) -> Result<String, PistonError> {
+        let output = Command::new(&self.adb_path)
+            .arg("-s")
+            .arg(&self.device_id)
+            .arg("shell")
+            .arg("date +'%m-%d %H:%M:%S.000'")
+            .output()
+            .map_err(|e| PistonError::ADBDevicesError(format!("Failed to run date: {}", e)))?;
+        if !output.status.success() {
+            return Err(PistonError::ADBDevicesError(format!(
+                "Failed to read the device time: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    //native crashes are logged by crash_dump under its own pid, so they never pass the --pid filter
+    fn print_crash(&self, pid: &str, since: &str) -> Result<(), PistonError> {
+        let output = Command::new(&self.adb_path)
+            .arg("-s")
+            .arg(&self.device_id)
+            .arg("logcat")
+            .arg("-b")
+            .arg("crash")
+            .arg("-d")
+            .arg("-v")
+            .arg("threadtime")
+            .arg("-t")
+            .arg(since)
+            .output()
+            .map_err(|e| {
+                PistonError::ADBDevicesError(format!("Failed to read the crash buffer: {}", e))
+            })?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let Some((kind, lines)) = Self::crash_block(&stdout, pid) else {
+            return Ok(());
+        };
+        println!("--- {} crash in {} (pid {}) ---", kind, self.package, pid);
+        for line in lines {
+            let line = match &self.symbolizer {
+                Some(symbolizer) => symbolizer.symbolicate_line(line),
+                None => line.to_string(),
+            };
+            println!("{}", Self::colorize(&line, self.color));
+        }
+        Ok(())
+    }
+
+    //the tombstone or FATAL EXCEPTION block of pid in the crash buffer, other crashes in the same window are skipped
+    fn crash_block<'a>(buffer: &'a str, pid: &str) -> Option<(&'static str, Vec<&'a str>)> {
+        //lines of concurrent crashes interleave, so group them by the logging process and tag
+        let mut blocks: Vec<(String, Vec<&str>)> = Vec::new();
+        for line in buffer.lines() {
+            let Some(source) = Self::line_source(line) else {
+                continue;
+            };
+            let starts = line.contains("*** *** ***") || line.contains("FATAL EXCEPTION");
+            match blocks.iter_mut().rev().find(|(key, _)| *key == source) {
+                Some((_, lines)) if !starts => lines.push(line),
+                _ => blocks.push((source, vec![line])),
+            }
+        }
+        blocks.into_iter().find_map(|(_, lines)| {
+            lines
+                .iter()
+                .find_map(|line| Self::crash_kind(line, pid))
+                .map(|kind| (kind, lines))
+        })
+    }
+
+    //pid and tag columns of a threadtime line, none for separators like "--------- beginning of crash"
+    fn line_source(line: &str) -> Option<String> {
+        let (header, _) = line.split_once(": ")?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let pid = fields
+            .get(2)
+            .filter(|pid| pid.chars().all(|c| c.is_ascii_digit()))?;
+        let tag = fields.get(5..).filter(|tag| !tag.is_empty())?.join(" ");
+        Some(format!("{} {}", pid, tag))
+    }
+
+    //tombstones name the pid as "pid: 1234, tid: ...", java crashes as "Process: <package>, PID: 1234",
+    //matched as whole numbers so pid 123 does not claim the crash of pid 1234
+    fn crash_kind(line: &str, pid: &str) -> Option<&'static str> {
+        [("pid: ", "native"), ("PID: ", "java")]
+            .into_iter()
+            .find(|(label, _)| {
+                line.match_indices(label).any(|(index, _)| {
+                    let number: String = line[index + label.len()..]
+                        .chars()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    number == pid
+                })
+            })
+            .map(|(_, kind)| kind)
+    }
+
+    fn spawn_logcat(&self, pid: &str) -> Result<Child, PistonError> {
+        Command::new(&self.adb_path)
+            .arg("-s")
+            .arg(&self.device_id)
+            .arg("logcat")
+            .arg("-v")
+            .arg("threadtime")
+            .arg(format!("--pid={}", pid))
+            .arg(format!("*:{}", self.level))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| PistonError::ADBDevicesError(format!("Failed to run logcat: {}", e)))
+    }
+
+    //threadtime lines look like "10-19 12:00:00.000  1234  1250 I tag: message"
+    fn colorize(line: &str, color: bool) -> String {
+        if !color {
+            return line.to_string();
+        }
+        let code = match line.split_whitespace().nth(4) {
+            Some("V") => "90",
+            Some("D") => "34",
+            Some("I") => "32",
+            Some("W") => "33",
+            Some("E") => "31",
+            Some("F") | Some("A") => "1;31",
+            _ => return line.to_string(),
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, line)
+    }
+}
+
+#[test]
+fn test_logcat_levels() {
+    assert_eq!(Logcat::parse_level("warn").unwrap(), 'W');
+    assert_eq!(Logcat::parse_level("E").unwrap(), 'E');
+    assert!(Logcat::parse_level("loud").is_err());
+    let line = "10-19 12:00:00.000  1234  1250 E RustStdoutStderr: panicked";
+    assert_eq!(
+        Logcat::colorize(line, true),
+        format!("\x1b[31m{}\x1b[0m", line)
+    );
+    assert_eq!(Logcat::colorize(line, false), line);
+    assert_eq!(
+        Logcat::colorize("--------- beginning of main", true),
+        "--------- beginning of main"
+    );
+}
+
+#[test]
+fn test_crash_kind() {
+    assert_eq!(
+        Logcat::crash_kind(
+            "F DEBUG   : pid: 1234, tid: 1250, name: RenderThread  >>> com.example.app <<<",
+            "1234"
+        ),
+        Some("native")
+    );
+    assert_eq!(
+        Logcat::crash_kind(
+            "E AndroidRuntime: Process: com.example.app, PID: 123",
+            "123"
+        ),
+        Some("java")
+    );
+    assert_eq!(
+        Logcat::crash_kind("E AndroidRuntime: Process: com.other.app, PID: 1234", "123"),
+        None
+    );
+    assert_eq!(
+        Logcat::crash_kind("F DEBUG   : pid: 1234, tid: 1250", "123"),
+        None
+    );
+}
+
+#[test]
+fn test_crash_block() {
+    //a native crash of another app interleaved with the java crash of pid 1234
+    let buffer = "--------- beginning of crash
+10-19 12:00:00.000  4321  4321 F DEBUG   : *** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***
+10-19 12:00:00.001  1234  1234 E AndroidRuntime: FATAL EXCEPTION: main
+10-19 12:00:00.002  4321  4321 F DEBUG   : pid: 999, tid: 999, name: other  >>> com.other.app <<<
+10-19 12:00:00.003  1234  1234 E AndroidRuntime: Process: com.example.app, PID: 1234
+10-19 12:00:00.004  4321  4321 F DEBUG   : signal 11 (SIGSEGV), code 1 (SEGV_MAPERR)
+10-19 12:00:00.005  1234  1234 E AndroidRuntime: java.lang.IllegalStateException: boom";
+    let (kind, lines) = Logcat::crash_block(buffer, "1234").unwrap();
+    assert_eq!(kind, "java");
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| line.contains("AndroidRuntime")));
+    let (kind, lines) = Logcat::crash_block(buffer, "999").unwrap();
+    assert_eq!(kind, "native");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[2].contains("SIGSEGV"));
+    assert!(Logcat::crash_block(buffer, "123").is_none());
+}
@@ -194,7 +194,7 @@ impl MacOSBuilder {
         //sync assets
         let bind = &self.assets.clone();
         let assets_src = Path::new(&bind);
-        Helper::sync_assets(assets_src, &assets_tgt)?;
+        Helper::sync_assets(assets_src, &assets_tgt, &[])?;
         //establish app icon target path ~/macos/release/Appname.app/Contents/Resources/macos_icon.icns
         let icon_path: PathBuf = res_path.join("macos_icon.icns");
         //establish Info.plist path ~/macos/release/Appname.app/Contents/Info.plist
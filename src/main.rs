@@ -1,23 +1,36 @@
-use crate::android::{AndroidBuilder, AndroidRunner};
+use crate::adb::Adb;
+use crate::android::{AndroidBuilder, AndroidRunner, AndroidTestRunner};
 use crate::devices::{AndroidDevice, Devices, IOSDevice};
 use crate::error::PistonError;
 use crate::helper::Helper;
 use crate::ios::{IOSBuilder, IOSRunner};
+use crate::keystore::Keystore;
 use crate::linux::{LinuxBuilder, LinuxRunner};
+use crate::logcat::Logcat;
 use crate::macos::{MacOSBuilder, MacOSRunner};
+use crate::play::{PlayClient, PlayRelease, PlayServiceAccount};
+use crate::symbolicate::Symbolizer;
 use crate::windows::WindowsBuilder;
 use cargo_subcommand::Subcommand;
 use clap::Parser;
 use std::env;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+mod adb;
 mod android;
+mod archive;
 mod asc;
 mod devices;
+mod elf;
 mod error;
 mod helper;
 mod ios;
+mod keystore;
 mod linux;
+mod logcat;
 mod macos;
+mod play;
+mod symbolicate;
 mod windows;
 
 pub type Result<T> = std::result::Result<T, PistonError>;
@@ -64,6 +77,194 @@ struct RunArgs {
     common: CommonArgs,
     #[clap(long)]
     device: Option<String>,
+    //stream the app's logs after launching it (android only)
+    #[clap(long)]
+    follow: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+struct LogcatArgs {
+    //device id, defaults to the first connected android device
+    #[clap(long)]
+    device: Option<String>,
+    //verbose, debug, info, warn, error or fatal
+    #[clap(long, default_value = "verbose")]
+    level: String,
+    //application id to follow, defaults to the package configured in Cargo.toml
+    #[clap(long)]
+    package: Option<String>,
+    //follow the release application id rather than the debug one
+    #[clap(long)]
+    release: bool,
+    //target triple whose unstripped libraries symbolicate native crashes, defaults to the device's abi
+    #[clap(long)]
+    target: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+struct TestArgs {
+    //device id or "android" for the first connected android device
+    #[clap(long)]
+    device: String,
+    //defaults to the device's primary abi
+    #[clap(long)]
+    target: Option<String>,
+    #[clap(long)]
+    release: bool,
+    //arguments passed to every test executable, e.g. -- --nocapture
+    #[clap(last = true)]
+    test_args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+struct DeviceArgs {
+    //device id, defaults to the first connected android device
+    #[clap(long, global = true)]
+    device: Option<String>,
+    //application id, defaults to the package configured in Cargo.toml
+    #[clap(long, global = true)]
+    package: Option<String>,
+    //target the release application id rather than the debug one
+    #[clap(long, global = true)]
+    release: bool,
+    #[clap(subcommand)]
+    action: DeviceAction,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, clap::Subcommand)]
+enum DeviceAction {
+    //Uninstall the app
+    Uninstall,
+    //Clear the app's data and cache
+    Clear,
+    //Force stop the app
+    ForceStop,
+    //Save a screenshot to target/piston/captures
+    Screenshot,
+    //Save a screen recording to target/piston/captures
+    Record {
+        #[clap(long, default_value_t = 10)]
+        seconds: u32,
+    },
+    //Copy a local file or directory into the app's data directory (debuggable builds only)
+    Push {
+        local: PathBuf,
+        remote: String,
+    },
+    //Copy a file out of the app's data directory (debuggable builds only)
+    Pull {
+        remote: String,
+        local: PathBuf,
+    },
+    //Let the device reach a host port at localhost:<device_port>
+    Reverse {
+        device_port: u16,
+        host_port: Option<u16>,
+        #[clap(long)]
+        remove: bool,
+    },
+    //Let the host reach a device port at localhost:<host_port>
+    Forward {
+        host_port: u16,
+        device_port: Option<u16>,
+        #[clap(long)]
+        remove: bool,
+    },
+    //Pair with a wireless debugging device using the host:port and code from its pairing dialog
+    Pair {
+        address: String,
+        code: String,
+    },
+    //Connect to a paired wireless debugging device and remember it
+    Connect {
+        address: String,
+    },
+    //Disconnect a wireless debugging device and forget it
+    Disconnect {
+        address: String,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+struct KeystoreArgs {
+    //use the release keystore rather than the debug keystore
+    #[clap(long, global = true)]
+    release: bool,
+    //alias to use, defaults to the alias configured in the .env
+    #[clap(long, global = true)]
+    alias: Option<String>,
+    #[clap(subcommand)]
+    action: KeystoreAction,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, clap::Subcommand)]
+enum KeystoreAction {
+    //Create the key if it does not exist yet
+    Create,
+    //List every alias in the keystore
+    List,
+    //Print the SHA-1 and SHA-256 certificate fingerprints
+    Fingerprint,
+    //Export the PEM certificate, e.g. the upload certificate for Play App Signing
+    ExportCert {
+        //defaults to target/piston/<alias>.pem
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+#[group(skip)]
+struct PublishArgs {
+    //store to publish to, only android (Google Play) is supported
+    #[clap(value_parser = ["android"])]
+    platform: String,
+    #[clap(long, default_value = "internal", value_parser = ["internal", "alpha", "beta", "production"])]
+    track: String,
+    #[clap(long, default_value = "aarch64-linux-android")]
+    target: String,
+    //release notes text, or a file containing them with --release-notes-file
+    #[clap(long)]
+    release_notes: Option<String>,
+    #[clap(long, conflicts_with = "release_notes")]
+    release_notes_file: Option<PathBuf>,
+    #[clap(long, default_value = "en-US")]
+    language: String,
+    //fraction of users for a staged rollout, e.g. 0.1
+    #[clap(long)]
+    rollout: Option<f64>,
+    //create the release as a draft to finish in Play Console
+    #[clap(long, conflicts_with = "rollout")]
+    draft: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+struct ExportArgs {
+    //project type to export, only android (Gradle) is supported
+    #[clap(value_parser = ["android"])]
+    platform: String,
+    //directory the project is written to
+    dir: PathBuf,
+    #[clap(long, default_value = "aarch64-linux-android")]
+    target: String,
+    #[clap(long)]
+    release: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+struct SymbolicateArgs {
+    //tombstone or saved logcat output
+    path: PathBuf,
+    #[clap(long, default_value = "aarch64-linux-android")]
+    target: String,
+    #[clap(long)]
+    release: bool,
 }
 
 #[derive(clap::Subcommand)]
@@ -77,6 +278,22 @@ enum PistonSubCmd {
     //List Devices function
     #[clap(visible_alias = "l")]
     ListDevices,
+    //Device test function
+    #[clap(visible_alias = "t")]
+    Test(TestArgs),
+    //Android device management function
+    #[clap(visible_alias = "d")]
+    Device(DeviceArgs),
+    //Android log streaming function
+    Logcat(LogcatArgs),
+    //Android keystore management function
+    Keystore(KeystoreArgs),
+    //Store publishing function
+    Publish(PublishArgs),
+    //IDE project export function
+    Export(ExportArgs),
+    //Android native crash symbolication function
+    Symbolicate(SymbolicateArgs),
     //Library Version function
     #[clap(visible_alias = "v")]
     Version,
@@ -435,7 +652,11 @@ fn main() -> Result<()> {
                 let target_device = tgt_unwrap.trim();
                 //explicit device flag can either be "ios" or "android" or the target device id
                 println!("run orders received for a target device: {}", &tgt_unwrap);
-                let devices = Devices::list_devices(env_vars.clone(), true)?;
+                //host:port ids are wireless devices that may not be connected yet
+                if Devices::is_network_address(target_device) {
+                    Devices::connect_android(&Devices::adb_path(&env_vars), target_device)?;
+                }
+                let devices = Devices::list_devices(env_vars.clone(), true, false)?;
                 let android_device: Option<&AndroidDevice> = devices
                     .android
                     .iter()
@@ -450,14 +671,16 @@ fn main() -> Result<()> {
                     IOSRunner::start(release, cwd, env_vars, &device)?;
                 //general Android target
                 } else if target_device == "android" && !devices.android.is_empty() {
-                    //TODO make this a smarter choice, instead of defaulting to first item in the vec
-                    let device = &devices.android[0];
+                    //first device that is ready for installs
+                    let device = devices.find_android(None)?;
                     println!("general Android runner target: {:?}", &device);
-                    AndroidRunner::start(release, cwd, env_vars, &device)?;
+                    AndroidRunner::start(release, args.follow, cwd, env_vars, device)?;
                 //explicit android target
-                } else if !android_device.is_none() {
-                    println!("explicit Android runner target: {:?}", &android_device);
-                    AndroidRunner::start(release, cwd, env_vars, &android_device.unwrap())?;
+                } else if android_device.is_some() {
+                    //refuse unauthorized or offline devices before building
+                    let device = devices.find_android(Some(target_device))?;
+                    println!("explicit Android runner target: {:?}", &device);
+                    AndroidRunner::start(release, args.follow, cwd, env_vars, device)?;
                 //explicit iOS target
                 } else if !ios_device.is_none() {
                     println!("explicit IOS runner target: {:?}", &ios_device);
@@ -469,7 +692,208 @@ fn main() -> Result<()> {
         }
         PistonSubCmd::ListDevices => {
             println!("list all available connected devices and relevant information");
-            Devices::list_devices(env_vars, false)?;
+            Devices::list_devices(env_vars, false, true)?;
+        }
+        PistonSubCmd::Test(args) => {
+            let devices = Devices::list_devices(env_vars.clone(), true, false)?;
+            let device: &AndroidDevice = devices.find_android(Some(&args.device))?;
+            let target = match args.target {
+                Some(target) => target,
+                None => device.target()?,
+            };
+            let exit_code = AndroidTestRunner::start(
+                args.release,
+                target,
+                cwd,
+                env_vars,
+                device,
+                args.test_args,
+            )?;
+            //mirror cargo test, failing tests fail the command
+            if exit_code != 0 {
+                std::process::exit(exit_code);
+            }
+        }
+        PistonSubCmd::Device(args) => {
+            let adb_path = Devices::adb_path(&env_vars);
+            //wireless connection management does not target an already connected device
+            let mut network_devices = Devices::network_devices(&env_vars);
+            match &args.action {
+                DeviceAction::Pair { address, code } => {
+                    return Devices::pair_android(&adb_path, address, code);
+                }
+                DeviceAction::Connect { address } => {
+                    Devices::connect_android(&adb_path, address)?;
+                    println!("connected to {}", address);
+                    if !network_devices.contains(address) {
+                        network_devices.push(address.clone());
+                        Helper::set_env_var("android_network_devices", &network_devices.join(","))?;
+                        println!("{} will be reconnected automatically", address);
+                    }
+                    return Ok(());
+                }
+                DeviceAction::Disconnect { address } => {
+                    let _ = Command::new(&adb_path)
+                        .arg("disconnect")
+                        .arg(address)
+                        .output();
+                    if network_devices.contains(address) {
+                        network_devices.retain(|device| device != address);
+                        Helper::set_env_var("android_network_devices", &network_devices.join(","))?;
+                    }
+                    println!("disconnected and forgot {}", address);
+                    return Ok(());
+                }
+                _ => {}
+            }
+            let devices = Devices::list_devices(env_vars.clone(), true, false)?;
+            let device: &AndroidDevice = devices.find_android(args.device.as_deref())?;
+            let adb = Adb::new(adb_path, device.id.clone());
+            let captures = cwd.join("target").join("piston").join("captures");
+            //only resolve the package from Cargo.toml for actions that need it
+            let package = || match &args.package {
+                Some(package) => Ok(package.clone()),
+                None => AndroidRunner::application_id(&cwd, args.release),
+            };
+            match args.action {
+                DeviceAction::Uninstall => adb.uninstall(&package()?)?,
+                DeviceAction::Clear => adb.clear_data(&package()?)?,
+                DeviceAction::ForceStop => adb.force_stop(&package()?)?,
+                DeviceAction::Screenshot => {
+                    adb.screenshot(&captures)?;
+                }
+                DeviceAction::Record { seconds } => {
+                    adb.screen_record(&captures, seconds)?;
+                }
+                DeviceAction::Push { local, remote } => {
+                    adb.push_to_app(&package()?, &local, &remote)?
+                }
+                DeviceAction::Pull { remote, local } => {
+                    adb.pull_from_app(&package()?, &remote, &local)?
+                }
+                DeviceAction::Reverse {
+                    device_port,
+                    host_port,
+                    remove,
+                } => adb.reverse(device_port, host_port.unwrap_or(device_port), remove)?,
+                DeviceAction::Forward {
+                    host_port,
+                    device_port,
+                    remove,
+                } => adb.forward(host_port, device_port.unwrap_or(host_port), remove)?,
+                DeviceAction::Pair { .. }
+                | DeviceAction::Connect { .. }
+                | DeviceAction::Disconnect { .. } => {}
+            }
+        }
+        PistonSubCmd::Logcat(args) => {
+            let devices = Devices::list_devices(env_vars.clone(), true, false)?;
+            let device: &AndroidDevice = devices.find_android(args.device.as_deref())?;
+            let package = match args.package {
+                Some(package) => package,
+                None => AndroidRunner::application_id(&cwd, args.release)?,
+            };
+            //logs still stream when the ndk tools or a supported device target are missing, crashes just stay unsymbolicated
+            let target = match args.target {
+                Some(target) => Ok(target),
+                None => device.target(),
+            };
+            let symbolizer = match target.and_then(|target| {
+                AndroidRunner::symbolizer(&cwd, &env_vars, &target, args.release)
+            }) {
+                Ok(symbolizer) => Some(symbolizer),
+                Err(e) => {
+                    println!("WARNING: native crashes will not be symbolicated: {}", e);
+                    None
+                }
+            };
+            Logcat::start(&env_vars, &device.id, &package, &args.level, symbolizer)?;
+        }
+        PistonSubCmd::Keystore(args) => {
+            let keystore = Keystore::from_hm(&env_vars, args.release)?.with_alias(args.alias);
+            let key_path = keystore.key.path.clone();
+            match args.action {
+                KeystoreAction::Create => {
+                    if Path::new(&key_path).exists() && keystore.has_alias()? {
+                        return Err(PistonError::KeyToolError(format!(
+                            "alias {} already exists in {}",
+                            keystore.key.alias, key_path
+                        )));
+                    }
+                    keystore.create()?;
+                }
+                KeystoreAction::List => {
+                    let entries = keystore.entries()?;
+                    println!("{} keystore: {}", keystore.kind(), key_path);
+                    for entry in entries {
+                        //mark the alias piston signs with
+                        let marker = if entry.alias == keystore.key.alias {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        println!("{} {} ({})", marker, entry.alias, entry.entry_type);
+                        println!("    Owner:       {}", entry.owner);
+                        println!("    Valid until: {}", entry.valid_until);
+                        println!("    SHA-256:     {}", entry.sha256);
+                    }
+                }
+                KeystoreAction::Fingerprint => {
+                    let entry = keystore.entry()?;
+                    println!("{} ({}, {})", entry.alias, keystore.kind(), key_path);
+                    println!("SHA-1:   {}", entry.sha1);
+                    println!("SHA-256: {}", entry.sha256);
+                }
+                KeystoreAction::ExportCert { output } => {
+                    let output = output.unwrap_or(
+                        cwd.join("target")
+                            .join("piston")
+                            .join(format!("{}.pem", keystore.key.alias)),
+                    );
+                    keystore.export_cert(&output)?;
+                }
+            }
+        }
+        PistonSubCmd::Publish(args) => {
+            if let Some(fraction) = args.rollout
+                && !(fraction > 0.0 && fraction < 1.0)
+            {
+                return Err(PistonError::Generic(format!(
+                    "rollout must be a fraction between 0 and 1, found: {}",
+                    fraction
+                )));
+            }
+            let release_notes = match &args.release_notes_file {
+                Some(path) => Some(std::fs::read_to_string(path).map_err(|e| {
+                    PistonError::Generic(format!("Failed to read {}: {}", path.display(), e))
+                })?),
+                None => args.release_notes.clone(),
+            };
+            let release = PlayRelease {
+                track: args.track,
+                release_notes: release_notes.map(|notes| notes.trim().to_string()),
+                language: args.language,
+                rollout: args.rollout,
+                draft: args.draft,
+            };
+            //fail on missing credentials before spending time on a release build
+            let client = PlayClient::new(PlayServiceAccount::from_hm(&env_vars)?, &env_vars);
+            let (output_path, app_name, package, _, _) =
+                AndroidBuilder::start(true, args.target, cwd, env_vars, None)?;
+            let aab_path = output_path.join(format!("{}.aab", app_name));
+            client.publish(&package, &aab_path, &release)?;
+        }
+        PistonSubCmd::Export(args) => {
+            let dir = cwd.join(&args.dir);
+            AndroidBuilder::export(args.release, args.target, cwd, env_vars, dir)?;
+        }
+        PistonSubCmd::Symbolicate(args) => {
+            let symbolizer: Symbolizer =
+                AndroidRunner::symbolizer(&cwd, &env_vars, &args.target, args.release)?;
+            let text = std::fs::read_to_string(&args.path).map_err(|e| {
+                PistonError::Generic(format!("Failed to read {}: {}", args.path.display(), e))
+            })?;
+            println!("{}", symbolizer.symbolicate(&text));
         }
         PistonSubCmd::Version => {
             println!("{}, {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
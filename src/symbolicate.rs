@@ -0,0 +1,151 @@
+use crate::error::PistonError;
+use crate::helper::Helper;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+//a "#00 pc 000000000004e2c4  /data/app/.../lib/arm64/libapp.so (...)" line of a tombstone backtrace
+#[derive(Debug, PartialEq)]
+struct Frame {
+    pc: String,
+    lib: String,
+}
+
+//resolves tombstone backtrace frames against the unstripped libraries cargo leaves in target/<triple>/<profile>
+pub struct Symbolizer {
+    tool: PathBuf,
+    libs_dir: PathBuf,
+}
+
+impl Symbolizer {
+    pub fn new(ndk_path: &str, libs_dir: PathBuf) -> Result<Self, PistonError> {
+        let host_platform = Helper::get_host_platform(ndk_path)?;
+        let bin = PathBuf::from(ndk_path)
+            .join("toolchains/llvm/prebuilt")
+            .join(host_platform)
+            .join("bin");
+        //both accept the same addr2line style flags, older ndks only ship llvm-addr2line
+        let tool = ["llvm-symbolizer", "llvm-addr2line"]
+            .iter()
+            .map(|name| bin.join(name))
+            .find(|path| path.exists())
+            .ok_or(PistonError::BuildError(format!(
+                "llvm-symbolizer or llvm-addr2line not found in {}",
+                bin.display()
+            )))?;
+        Ok(Symbolizer { tool, libs_dir })
+    }
+
+    fn parse_frame(line: &str) -> Option<Frame> {
+        let mut tokens = line.split_whitespace().skip_while(|t| !t.starts_with('#'));
+        let index = tokens.next()?;
+        if index.len() < 2 || !index[1..].chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        if tokens.next()? != "pc" {
+            return None;
+        }
+        let pc = tokens.next()?;
+        if !pc.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        //libraries loaded straight from the apk show up as base.apk!libapp.so
+        let lib = tokens.next()?.rsplit(['/', '!']).next()?;
+        Some(Frame {
+            pc: pc.to_string(),
+            lib: lib.to_string(),
+        })
+    }
+
+    //the line itself followed by its demangled, inlining aware source frames when the library is ours
+    pub fn symbolicate_line(&self, line: &str) -> String {
+        let frame = match Self::parse_frame(line) {
+            Some(frame) => frame,
+            None => return line.to_string(),
+        };
+        let lib_path = self.libs_dir.join(&frame.lib);
+        if !lib_path.exists() {
+            return line.to_string();
+        }
+        match self.resolve(&lib_path, &frame.pc) {
+            Ok(resolved) if !resolved.is_empty() => {
+                let mut out = line.to_string();
+                for (function, location) in resolved {
+                    out.push_str(&format!("\n        at {} ({})", function, location));
+                }
+                out
+            }
+            Ok(_) => line.to_string(),
+            Err(e) => format!("{}\n        symbolication failed: {}", line, e),
+        }
+    }
+
+    pub fn symbolicate(&self, text: &str) -> String {
+        text.lines()
+            .map(|line| self.symbolicate_line(line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn resolve(&self, lib_path: &Path, pc: &str) -> Result<Vec<(String, String)>, PistonError> {
+        let output = Command::new(&self.tool)
+            .arg("-f")
+            .arg("-i")
+            .arg("-C")
+            .arg("-e")
+            .arg(lib_path)
+            .arg(format!("0x{}", pc))
+            .output()
+            .map_err(|e| {
+                PistonError::Generic(format!("Failed to run {}: {}", self.tool.display(), e))
+            })?;
+        if !output.status.success() {
+            return Err(PistonError::Generic(format!(
+                "{} failed: {}",
+                self.tool.display(),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(Self::parse_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    //function and file:line pairs, innermost inlined frame first
+    fn parse_output(stdout: &str) -> Vec<(String, String)> {
+        let lines: Vec<&str> = stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        lines
+            .chunks(2)
+            .filter(|pair| pair.len() == 2 && pair[0] != "??")
+            .map(|pair| (pair[0].to_string(), pair[1].to_string()))
+            .collect()
+    }
+}
+
+#[test]
+fn test_parse_frames() {
+    let logcat = "10-19 12:00:00.000  4321  4321 F DEBUG   :       #01 pc 000000000004e2c4  /data/app/~~a==/com.example.app-b==/lib/arm64/libapp.so (BuildId: 1234)";
+    assert_eq!(
+        Symbolizer::parse_frame(logcat),
+        Some(Frame {
+            pc: "000000000004e2c4".to_string(),
+            lib: "libapp.so".to_string(),
+        })
+    );
+    let tombstone = "      #00 pc 0000000000001a2b  /data/app/base.apk!libapp.so (offset 0x4000)";
+    assert_eq!(Symbolizer::parse_frame(tombstone).unwrap().lib, "libapp.so");
+    assert!(Symbolizer::parse_frame("backtrace:").is_none());
+    assert_eq!(
+        Symbolizer::parse_output(
+            "app::render::draw\n/src/render.rs:42:9\napp::main\n/src/lib.rs:10:5\n\n"
+        ),
+        vec![
+            (
+                "app::render::draw".to_string(),
+                "/src/render.rs:42:9".to_string()
+            ),
+            ("app::main".to_string(), "/src/lib.rs:10:5".to_string()),
+        ]
+    );
+}
@@ -135,7 +135,7 @@ impl WindowsBuilder {
         let bind = &self.assets.clone();
         let assets_src = Path::new(&bind);
         let assets_tgt = path.join("assets");
-        Helper::sync_assets(assets_src, &assets_tgt)?;
+        Helper::sync_assets(assets_src, &assets_tgt, &[])?;
         //if icon path was provided...embed
         if self.embed_resources_ok {
             println!("icon path provided and embed resources installed, configuring icon");